@@ -5,10 +5,10 @@ use renet::{
 };
 use std::{net::UdpSocket, time::SystemTime};
 use store::{
-    camera::CameraPlugin,
+    camera::{CameraPlugin, MirroredView},
     game_objects::GameObjectsPlugin,
-    map::{components::MouseCubePos, HexPlugin},
-    GameEvent, GameStage, GameState, WhoAmI,
+    map::{components::HoverState, HexPlugin},
+    GameCommand, GameEvent, GameStage, GameState, WhoAmI,
 };
 
 use ui::UiPlugin;
@@ -66,29 +66,46 @@ fn main() {
 
 ////////// SETUP /////////////
 fn setup(mut commands: Commands, client: Res<RenetClient>) {
-    commands.insert_resource(WhoAmI(client.client_id()));
+    commands.insert_resource(WhoAmI(client.client_id().into()));
 }
 
 /////////// UPDATE SYSTEMTS /////////////
 
 fn input(
     input: Res<Input<MouseButton>>,
-    // kb_input: Res<Input<KeyCode>>,
-    ms_coord_pos: Res<MouseCubePos>,
+    kb_input: Res<Input<KeyCode>>,
+    hover: Res<HoverState>,
     game_state: Res<GameState>,
     mut client: ResMut<RenetClient>,
 ) {
-    // If left mouse button is pressed, send mouse world pos
+    // Press Enter in the lobby to tell the server we are ready to play
+    if kb_input.just_pressed(KeyCode::Return) && game_state.stage == GameStage::Lobby {
+        let command = GameCommand::PlayerReady;
+        client.send_message(0, serde_json::to_vec(&command).unwrap());
+    }
+
+    // Press Space during our turn to pass the rest of it
+    if kb_input.just_pressed(KeyCode::Space) && game_state.stage == GameStage::InGame {
+        let command = GameCommand::EndTurn;
+        if game_state
+            .resolve(command.clone(), client.client_id().into())
+            .is_ok()
+        {
+            client.send_message(0, serde_json::to_vec(&command).unwrap());
+        }
+    }
+
+    // If left mouse button is pressed over a hex, attack it. Clicks off the board or on the
+    // padding between hexes are ignored
     if input.just_pressed(MouseButton::Left) {
         // We only want to handle inputs once we are ingame
         match game_state.stage {
             store::GameStage::PreGame => {}
             store::GameStage::InGame => {
-                let event = GameEvent::ShipMove {
-                    player_id: client.client_id(),
-                    at: ms_coord_pos.0,
-                };
-                client.send_message(0, bincode::serialize(&event).unwrap());
+                if let Some(at) = hover.coords {
+                    let command = GameCommand::Attack { at };
+                    client.send_message(0, serde_json::to_vec(&command).unwrap());
+                }
             }
             _ => {
                 return;
@@ -97,21 +114,53 @@ fn input(
     }
 }
 
-fn update_board(mut game_stage: ResMut<State<GameStage>>, mut game_events: EventReader<GameEvent>) {
+fn update_board(
+    mut game_stage: ResMut<State<GameStage>>,
+    mut game_events: EventReader<GameEvent>,
+    mut mirrored: ResMut<MirroredView>,
+    game_state: Res<GameState>,
+    who_am_i: Res<WhoAmI>,
+) {
     for event in game_events.iter() {
         match event {
             GameEvent::ShipMove { player_id, at } => {
                 info!("{:?} moved to {:?}", player_id, at);
             }
             GameEvent::ShipPlaced { .. } => {}
+            GameEvent::PlaceMultiple {
+                player_id,
+                placements,
+            } => {
+                info!("{:?} placed {} ships", player_id, placements.len());
+            }
+            GameEvent::Attack { player_id, at } => {
+                info!("{:?} fired at {:?}", player_id, at);
+            }
+            GameEvent::PlacementCommitted { player_id } => {
+                info!("{:?} finished placing ships", player_id);
+            }
+            GameEvent::UndoPlacement { player_id } => {
+                info!("{:?} took back a ship", player_id);
+            }
+            GameEvent::EndTurn { player_id } => {
+                info!("{:?} ended their turn", player_id);
+            }
+            GameEvent::RequestRematch { player_id } => {
+                info!("{:?} wants a rematch", player_id);
+            }
             GameEvent::BeginGame { .. } => {
                 info!("GAME STARTED!");
                 game_stage.set(GameStage::InGame).unwrap();
             }
             GameEvent::EndGame { .. } => todo!(),
             GameEvent::PlayerJoined { .. } => {}
+            GameEvent::PlayerReady { player_id } => {
+                info!("{:?} is ready", player_id);
+            }
             GameEvent::PlayerDisconnected { .. } => todo!(),
             GameEvent::SetupBoard => {
+                // the second player sits across the board
+                mirrored.0 = game_state.active_player_ids().nth(1) == Some(who_am_i.0);
                 game_stage.set(GameStage::PreGame).unwrap();
             }
         }
@@ -165,11 +214,14 @@ fn receive_events_from_server(
 ) {
     while let Some(message) = client.receive_message(0) {
         // Whenever the server sends a message we know it must be a game event
-        let event: GameEvent = bincode::deserialize(&message).unwrap();
+        let event: GameEvent = serde_json::from_slice(&message).unwrap();
         trace!("{:#?}", event);
 
         // We trust the server, no need to validade events
-        game_state.consume(&event);
+        if let Err(err) = game_state.consume(&event) {
+            error!("Could not consume {:#?}: {}", event, err);
+            continue;
+        }
 
         // Send the event into the bevy event system so systems can react to it
         game_events.send(event);
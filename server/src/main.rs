@@ -52,49 +52,50 @@ fn main() {
             match event {
                 ServerEvent::ClientConnected(id, user_data) => {
                     // Tell the recently joined player about the other player
-                    for (player_id, player) in game_state.players.iter() {
+                    for (player_id, player) in game_state.players_sorted() {
                         let event = store::GameEvent::PlayerJoined {
-                            player_id: *player_id,
+                            player_id,
                             player_details: player.clone(),
                         };
-                        server.send_message(id, 0, bincode::serialize(&event).unwrap());
+                        server.send_message(id, 0, serde_json::to_vec(&event).unwrap());
                     }
 
                     // Add the new player to the game
                     let event = store::GameEvent::PlayerJoined {
-                        player_id: id,
+                        player_id: id.into(),
                         player_details: name_from_user_data(&user_data),
                     };
-                    game_state.consume(&event);
+                    if let Err(err) = game_state.consume(&event) {
+                        warn!("Could not add client {}: {}", id, err);
+                        continue;
+                    }
 
                     // Tell all players that a new player has joined
-                    server.broadcast_message(0, bincode::serialize(&event).unwrap());
+                    server.broadcast_message(0, serde_json::to_vec(&event).unwrap());
 
                     info!("Client {} connected.", id);
-
-                    // Begin game with two players
-                    // TODO: implement "start button in lobby"
-                    // TODO: players may join in the middle of a game
-                    if game_state.players.len() == 2 {
-                        let event = store::GameEvent::SetupBoard;
-                        game_state.consume(&event);
-                        server.broadcast_message(0, bincode::serialize(&event).unwrap());
-                        trace!("Player setup ship positions");
-                    }
                 }
                 ServerEvent::ClientDisconnected(id) => {
                     // First consume a disconnect event
-                    let event = store::GameEvent::PlayerDisconnected { player_id: id };
-                    game_state.consume(&event);
-                    server.broadcast_message(0, bincode::serialize(&event).unwrap());
+                    let event = store::GameEvent::PlayerDisconnected {
+                        player_id: id.into(),
+                    };
+                    if let Err(err) = game_state.consume(&event) {
+                        warn!("Could not remove client {}: {}", id, err);
+                    }
+                    server.broadcast_message(0, serde_json::to_vec(&event).unwrap());
                     info!("Client {} disconnected", id);
 
                     // Then end the game
                     let event = store::GameEvent::EndGame {
-                        reason: EndGameReason::PlayerLeft { player_id: id },
+                        reason: EndGameReason::PlayerLeft {
+                            player_id: id.into(),
+                        },
                     };
-                    game_state.consume(&event);
-                    server.broadcast_message(0, bincode::serialize(&event).unwrap());
+                    if let Err(err) = game_state.consume(&event) {
+                        warn!("Could not end the game: {}", err);
+                    }
+                    server.broadcast_message(0, serde_json::to_vec(&event).unwrap());
 
                     // NOTE: Since we don't authenticate users we can't do any reconnection attempts.
                     // We simply have no way to know if the next user is the same as the one that disconnected.
@@ -102,25 +103,55 @@ fn main() {
             }
         }
 
-        // Receive GameEvents from clients. Broadcast valid events.
+        // Receive GameCommands from clients. Broadcast the events of valid commands.
         for client_id in server.clients_id().into_iter() {
             while let Some(message) = server.receive_message(client_id, 0) {
-                if let Ok(event) = bincode::deserialize::<store::GameEvent>(&message) {
-                    if game_state.validade(&event) {
-                        game_state.consume(&event);
-                        trace!("Player {} sent: \n\t{:#?}", client_id, event);
-                        server.broadcast_message(0, bincode::serialize(&event).unwrap());
-
-                        // Determine if a player has won the game
-                        // if let Some(winner) = game_state.determine_winner() {
-                        //     let event = store::GameEvent::EndGame {
-                        //         reason: store::EndGameReason::PlayerWon { winner },
-                        //     };
-                        //     server.broadcast_message(0, bincode::serialize(&event).unwrap());
-                        // }
-                    } else {
-                        warn!("Player {} sent invalid event:\n\t{:#?}", client_id, event);
+                if let Ok(command) = serde_json::from_slice::<store::GameCommand>(&message) {
+                    let event = command.clone().into_event(client_id.into());
+                    let stage = game_state.stage;
+                    if let Err(reason) = game_state.apply_from(event.clone(), client_id.into()) {
+                        warn!(
+                            "Player {} sent invalid command {:?}:\n\t{:#?}",
+                            client_id, reason, command
+                        );
+                        // the player forfeited under InvalidActionPolicy::Forfeit
+                        if game_state.stage != stage {
+                            if let Some(end) = game_state.history.last() {
+                                server.broadcast_message(0, serde_json::to_vec(end).unwrap());
+                            }
+                        }
+                        continue;
                     }
+                    trace!("Player {} sent: \n\t{:#?}", client_id, event);
+                    server.broadcast_message(0, serde_json::to_vec(&event).unwrap());
+
+                    // Setup the board once both players are ready
+                    // TODO: players may join in the middle of a game
+                    let setup = store::GameEvent::SetupBoard;
+                    if game_state.validade(&setup) && game_state.consume(&setup).is_ok() {
+                        server.broadcast_message(0, serde_json::to_vec(&setup).unwrap());
+                        trace!("Player setup ship positions");
+                    }
+
+                    // Begin the game once every player committed their placement. The
+                    // player to commit last lets the other one take the first turn.
+                    if let store::GameEvent::PlacementCommitted { player_id } = event {
+                        let begin = store::GameEvent::BeginGame {
+                            first_player: player_id,
+                        };
+                        if game_state.validade(&begin) && game_state.consume(&begin).is_ok() {
+                            server.broadcast_message(0, serde_json::to_vec(&begin).unwrap());
+                            trace!("Game started");
+                        }
+                    }
+
+                    // Determine if a player has won the game
+                    // if let Some(winner) = game_state.determine_winner() {
+                    //     let event = store::GameEvent::EndGame {
+                    //         reason: store::EndGameReason::PlayerWon { winner },
+                    //     };
+                    //     server.broadcast_message(0, serde_json::to_vec(&event).unwrap());
+                    // }
                 }
             }
         }
@@ -136,7 +167,5 @@ fn name_from_user_data(user_data: &[u8; NETCODE_USER_DATA_BYTES]) -> Player {
     let mut len = u64::from_le_bytes(buffer) as usize;
     len = len.min(NETCODE_USER_DATA_BYTES - 8);
     let data = user_data[8..len + 8].to_vec();
-    Player {
-        name: String::from_utf8(data).unwrap(),
-    }
+    Player::new(String::from_utf8(data).unwrap())
 }
@@ -0,0 +1,74 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    game_objects::{get_max_grid_rotation, get_object_all_coords, PlacedShip},
+    map::components::{CubeCoords, HexMap},
+    GameEvent, GameState, PlayerId,
+};
+
+/// Extra weight a candidate ship position earns for every known hit it covers, so the bot
+/// hunts around damaged ships before searching the rest of the board.
+const HIT_WEIGHT: u32 = 10;
+
+/// "Hard" difficulty targeting. Counts, for every cell, how many positions of the enemy ships
+/// still afloat could cover it given what `player_id` learned from their shots, and fires at
+/// the cell with the highest count. Returns None if there is no cell the player may attack.
+pub fn density_target(
+    state: &GameState,
+    board: &HexMap,
+    player_id: PlayerId,
+) -> Option<CubeCoords> {
+    let board_cells: HashSet<CubeCoords> = board.coords_iter().collect();
+    let shots = state
+        .player_shots
+        .get(&player_id)
+        .cloned()
+        .unwrap_or_default();
+    let enemy_ships: Vec<&PlacedShip> = state.enemy_ships(&player_id).collect();
+
+    // what the player knows: which shots hit, which missed and which ships went down
+    let sunk_cells: HashSet<CubeCoords> = enemy_ships
+        .iter()
+        .filter(|ship| ship.is_sunk())
+        .flat_map(|ship| ship.cells.iter().map(|(coords, _)| *coords))
+        .collect();
+    let (hits, misses): (HashSet<CubeCoords>, HashSet<CubeCoords>) = shots
+        .iter()
+        .filter(|coords| !sunk_cells.contains(coords))
+        .partition(|coords| {
+            enemy_ships
+                .iter()
+                .any(|ship| ship.cells.iter().any(|(cell, _)| cell == *coords))
+        });
+
+    let mut density: HashMap<CubeCoords, u32> = HashMap::new();
+    for ship in enemy_ships.iter().filter(|ship| !ship.is_sunk()) {
+        for origin in &board_cells {
+            for rotation in 0..get_max_grid_rotation(&ship.ship_type) {
+                let footprint = get_object_all_coords(&ship.ship_type, rotation, origin);
+                let impossible = footprint.iter().any(|coords| {
+                    !board_cells.contains(coords)
+                        || misses.contains(coords)
+                        || sunk_cells.contains(coords)
+                });
+                if impossible {
+                    continue;
+                }
+                let covered_hits = footprint.iter().filter(|c| hits.contains(c)).count() as u32;
+                for coords in footprint {
+                    *density.entry(coords).or_insert(0) += 1 + HIT_WEIGHT * covered_hits;
+                }
+            }
+        }
+    }
+
+    board
+        .coords_iter()
+        .filter(|coords| {
+            state.validade(&GameEvent::Attack {
+                player_id,
+                at: *coords,
+            })
+        })
+        .max_by_key(|coords| density.get(coords).copied().unwrap_or(0))
+}
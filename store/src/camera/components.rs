@@ -7,3 +7,8 @@ pub struct MainCamera;
 pub struct MouseWorldPos(pub Vec2);
 
 impl Resource for MouseWorldPos {}
+
+/// Whether the board is drawn turned half a turn, so the player across the board sees their
+/// side closest to them. Each client sets it for its own player.
+#[derive(Debug, Default, Resource)]
+pub struct MirroredView(pub bool);
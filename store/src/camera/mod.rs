@@ -11,8 +11,10 @@ pub struct CameraPlugin;
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(MouseWorldPos::default())
+            .insert_resource(MirroredView::default())
             .add_startup_system(camera_setup)
             .add_system(camera_system)
+            .add_system(camera_mirror)
             .add_system(mouse_to_world_pos);
     }
 }
@@ -4,6 +4,8 @@ pub use bevy::{
 };
 
 pub use super::components::*;
+use crate::map::components::HexMap;
+use std::f32::consts::PI;
 
 pub fn camera_system(
     time: Res<Time>,
@@ -46,7 +48,30 @@ pub fn camera_system(
     if transf.scale.x < 1.0 {
         transf.scale = Vec3::splat(1.)
     }
-    transf.translation += time.delta_seconds() * direction * 100.;
+    // pan along the screen axes, which the mirrored view turns around
+    let rotation = transf.rotation;
+    transf.translation += rotation * (time.delta_seconds() * direction * 100.);
+}
+
+/// Turns the camera half a turn around the board center whenever [`MirroredView`] changes.
+/// Only the camera moves, so mouse picking keeps landing on the right cells.
+pub fn camera_mirror(
+    mirrored: Res<MirroredView>,
+    hex_map: Option<Res<HexMap>>,
+    mut query: Query<&mut Transform, With<MainCamera>>,
+) {
+    let hex_map = match hex_map {
+        Some(hex_map) if mirrored.is_changed() => hex_map,
+        _ => return,
+    };
+    let angle = if mirrored.0 { PI } else { 0.0 };
+    for mut transf in &mut query {
+        let rotation = Quat::from_rotation_z(angle);
+        if transf.rotation.angle_between(rotation) > f32::EPSILON {
+            transf.translation = hex_map.mirror_world(transf.translation);
+            transf.rotation = rotation;
+        }
+    }
 }
 
 pub fn mouse_to_world_pos(
@@ -13,13 +13,38 @@ pub struct GridMaxRotation(pub u8);
 #[derive(Component)]
 pub struct AngularRot(pub i32);
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Component)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Component)]
 pub enum GameObject {
     Boat,
     Ship,
     Cruizer,
 }
 
+impl GameObject {
+    /// Every kind of object, smallest first.
+    pub fn all() -> &'static [GameObject] {
+        &[GameObject::Boat, GameObject::Ship, GameObject::Cruizer]
+    }
+
+    /// How many cells the object covers on the board.
+    pub fn cells(&self) -> u32 {
+        match self {
+            GameObject::Boat => 2,
+            GameObject::Ship => 3,
+            GameObject::Cruizer => 4,
+        }
+    }
+
+    /// How many hits each cell of the object takes before it is destroyed.
+    pub fn armor(&self) -> u8 {
+        match self {
+            GameObject::Boat => 1,
+            GameObject::Ship => 1,
+            GameObject::Cruizer => 2,
+        }
+    }
+}
+
 #[derive(Bundle)]
 pub struct ObjectBundle {
     pub game_object: GameObject,
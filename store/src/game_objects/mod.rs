@@ -8,13 +8,13 @@ use bevy::{
     prelude::*,
     render::{mesh::Indices, render_resource::PrimitiveTopology},
 };
-// use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize};
 
 pub use components::*;
 use renet::RenetClient;
 
 use crate::map::{
-    components::{CubeCoords, Hexagon},
+    components::{CubeCoords, Hexagon, HoverState},
     HEX_CONFIG_PADDING, HEX_CONFIG_SIZE,
 };
 
@@ -25,9 +25,176 @@ pub const SHIPS: [GameObject; 4] = [
     GameObject::Boat,
 ];
 
+/// Cells covered by a whole fleet once it is placed.
+pub fn total_cells(fleet: &[GameObject]) -> u32 {
+    fleet.iter().map(GameObject::cells).sum()
+}
+
+/// The ships each player places before the game begins, in placement order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fleet(pub Vec<GameObject>);
+
+impl Fleet {
+    /// The fleet of a regular game, see [`SHIPS`].
+    pub fn standard() -> Self {
+        Self(SHIPS.to_vec())
+    }
+
+    /// Cells covered by the whole fleet once it is placed.
+    pub fn total_cells(&self) -> u32 {
+        total_cells(&self.0)
+    }
+
+    pub fn contains(&self, ship_type: GameObject) -> bool {
+        self.0.contains(&ship_type)
+    }
+
+    /// Number of ships in the fleet.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The same ships in an order picked from `seed`. A seed always gives the same order, so
+    /// every player and every replay of a game places them alike.
+    pub fn shuffled(&self, seed: u64) -> Self {
+        // splitmix64, enough to pick an order and keeps the crate free of an rng dependency
+        let mut state = seed;
+        let mut next = || {
+            state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^ (z >> 31)
+        };
+        let mut ships = self.0.clone();
+        for i in (1..ships.len()).rev() {
+            let j = (next() % (i as u64 + 1)) as usize;
+            ships.swap(i, j);
+        }
+        Self(ships)
+    }
+}
+
+impl Default for Fleet {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+impl IntoIterator for Fleet {
+    type Item = GameObject;
+    type IntoIter = std::vec::IntoIter<GameObject>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Fleet {
+    type Item = &'a GameObject;
+    type IntoIter = std::slice::Iter<'a, GameObject>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
 #[derive(Resource)]
 pub struct Garage(pub Vec<GameObject>);
 
+/// Cells the next ship of the garage would cover if placed at the hovered hex with the given
+/// rotation. None when nothing is hovered or the garage is empty.
+pub fn placement_preview(
+    hover: &HoverState,
+    garage: &Garage,
+    rotation: i32,
+) -> Option<Vec<CubeCoords>> {
+    let at = hover.coords?;
+    let ship = garage.0.last()?;
+    Some(get_object_all_coords(ship, rotation, &at))
+}
+
+/// A ship placed on the board, with the armor left on each of the cells it covers.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlacedShip {
+    pub ship_type: GameObject,
+    pub at: CubeCoords,
+    pub rotation: i32,
+    pub cells: Vec<(CubeCoords, u8)>,
+    /// Whether this is the ship that loses the game when sunk under
+    /// [`crate::WinCondition::SinkFlagship`]. Every player's first ship is their flagship.
+    #[serde(default)]
+    pub is_flagship: bool,
+}
+
+impl PlacedShip {
+    pub fn new(ship_type: GameObject, at: CubeCoords, rotation: i32) -> Self {
+        let cells = get_object_all_coords(&ship_type, rotation, &at)
+            .into_iter()
+            .map(|coords| (coords, ship_type.armor()))
+            .collect();
+        Self {
+            ship_type,
+            at,
+            rotation,
+            cells,
+            is_flagship: false,
+        }
+    }
+
+    /// Whether the ship has a cell at `coords` that is not destroyed yet.
+    pub fn is_standing_at(&self, coords: &CubeCoords) -> bool {
+        self.cells
+            .iter()
+            .any(|(cell, armor)| cell == coords && *armor > 0)
+    }
+
+    /// Take one point of armor from the cell at `coords`. Returns whether the ship was hit.
+    pub fn hit(&mut self, coords: &CubeCoords) -> bool {
+        match self
+            .cells
+            .iter_mut()
+            .find(|(cell, armor)| cell == coords && *armor > 0)
+        {
+            Some((_, armor)) => {
+                *armor -= 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Copy of the ship as seen on a board view turned `steps` sixths of a turn clockwise around
+    /// its center, for rendering only. Negative steps turn it counterclockwise.
+    pub fn rotated_view(&self, steps: i32) -> PlacedShip {
+        let turns = steps.rem_euclid(6);
+        let rotate = |coords: &CubeCoords| coords.rotate_n(turns);
+        // rotations go counterclockwise, a sixth of a turn every `cells() - 1` steps
+        let max_rotation = get_max_grid_rotation(&self.ship_type);
+        let rotation_steps = turns * (self.ship_type.cells() as i32 - 1);
+        PlacedShip {
+            ship_type: self.ship_type,
+            at: rotate(&self.at),
+            rotation: (self.rotation - rotation_steps).rem_euclid(max_rotation),
+            cells: self
+                .cells
+                .iter()
+                .map(|(coords, armor)| (rotate(coords), *armor))
+                .collect(),
+            is_flagship: self.is_flagship,
+        }
+    }
+
+    /// A ship is sunk once all of its cells are destroyed.
+    pub fn is_sunk(&self) -> bool {
+        self.cells.iter().all(|(_, armor)| *armor == 0)
+    }
+}
+
 pub struct GameObjectsPlugin;
 impl Plugin for GameObjectsPlugin {
     fn build(&self, app: &mut App) {
@@ -41,11 +208,11 @@ impl Plugin for GameObjectsPlugin {
     }
 }
 
-fn populate_garage(mut commands: Commands) {
-    let mut garage = Vec::new();
-    for s in SHIPS {
-        garage.push(s);
-    }
+/// Fills the garage in the order the server expects ships to be placed, see
+/// [`GameState::garage_order`]. Ships are popped from the back, hence the reversal.
+fn populate_garage(mut commands: Commands, game_state: Res<GameState>) {
+    let mut garage = game_state.garage_order().0;
+    garage.reverse();
     commands.insert_resource(Garage(garage));
 }
 
@@ -209,14 +376,11 @@ pub fn get_object_all_coords(
 
 fn hex_end_rotate(rotation: i32, object_len: u32) -> CubeCoords {
     let max_valid_rotations = 6 * (object_len - 1);
-    let mut u_rotation = rotation;
-    if u_rotation < 0 {
-        u_rotation = max_valid_rotations as i32 + u_rotation;
-    }
+    // any rotation wraps around, so this never indexes out of the coordinate vector
+    let u_rotation = rotation.rem_euclid(max_valid_rotations as i32) as u32;
     let coord_vector = build_coordinate_vector(object_len);
     let q = coord_vector[u_rotation as usize];
-    let r =
-        coord_vector[((u_rotation as u32 + 2 * (object_len - 1)) % max_valid_rotations) as usize];
+    let r = coord_vector[((u_rotation + 2 * (object_len - 1)) % max_valid_rotations) as usize];
     let s = -r - q;
     CubeCoords { q, r, s }
 }
@@ -271,3 +435,73 @@ pub fn line_coords(coord_origin: CubeCoords, coord_end: CubeCoords) -> Vec<CubeC
 fn lerp(start: Vec3, end: Vec3, distance: f32) -> Vec3 {
     start + (end - start) * distance
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn armored_cell_survives_the_first_hit() {
+        let mut ship = PlacedShip::new(GameObject::Cruizer, CubeCoords::ZERO, 0);
+        let cell = ship.cells[0].0;
+        assert_eq!(ship.cells[0].1, 2);
+
+        assert!(ship.hit(&cell));
+        assert!(ship.is_standing_at(&cell));
+        assert!(!ship.is_sunk());
+
+        assert!(ship.hit(&cell));
+        assert!(!ship.is_standing_at(&cell));
+        // a destroyed cell takes no more hits
+        assert!(!ship.hit(&cell));
+
+        let cells: Vec<CubeCoords> = ship.cells.iter().map(|(cell, _)| *cell).collect();
+        for cell in &cells[1..] {
+            ship.hit(cell);
+            ship.hit(cell);
+        }
+        assert!(ship.is_sunk());
+    }
+
+    #[test]
+    fn ship_cells_follow_the_rotation() {
+        let origin = CubeCoords { q: 1, r: -2, s: 1 };
+        let along = |dir: CubeCoords| vec![origin, origin + dir, origin + dir + dir];
+        let axis = CubeCoords { q: 0, r: 1, s: -1 };
+        let ship = GameObject::Ship;
+        assert_eq!(get_object_all_coords(&ship, 0, &origin), along(axis));
+
+        // a single rotation turns a direction into the next one either way
+        let (mut left, mut right) = (axis, axis);
+        left.rotate_left();
+        right.rotate_right();
+        assert_eq!(left, CubeCoords { q: 1, r: 0, s: -1 });
+        assert_eq!(right, CubeCoords { q: -1, r: 1, s: 0 });
+
+        // placements turn ships counterclockwise, to the next hex direction every
+        // `cells() - 1` steps, so rotation 1 already does for a boat
+        assert_eq!(
+            get_object_all_coords(&GameObject::Boat, 1, &origin),
+            vec![origin, origin + left]
+        );
+        let mut dir = axis;
+        for sixth in 0..6 {
+            assert_eq!(get_object_all_coords(&ship, sixth * 2, &origin), along(dir));
+            dir.rotate_left();
+        }
+        assert_eq!(dir, axis);
+    }
+
+    #[test]
+    fn shuffled_fleet_is_the_same_for_a_seed() {
+        let fleet = Fleet::standard();
+        for seed in 0..16 {
+            let order = fleet.shuffled(seed);
+            assert_eq!(order, fleet.shuffled(seed));
+            let count = |fleet: &Fleet, ship| fleet.0.iter().filter(|&&s| s == ship).count();
+            for &ship in &fleet.0 {
+                assert_eq!(count(&order, ship), count(&fleet, ship));
+            }
+        }
+    }
+}
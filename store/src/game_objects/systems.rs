@@ -7,13 +7,13 @@ use bevy::{input::mouse::MouseWheel, prelude::*};
 use crate::{
     map::{
         self,
-        components::{HexMapObjects, HexMapTiles, Hexagon, MouseCubePos},
+        components::{HexMapObjects, HexMapTiles, Hexagon, HoverState, MouseCubePos},
         HEX_CONFIG_PADDING, HEX_CONFIG_SIZE,
     },
-    GameEvent, WhoAmI,
+    GameCommand, GameEvent, WhoAmI,
 };
 
-use super::{AngularRot, GameObject, GridMaxRotation, MouseFollow, ObjectHover};
+use super::{AngularRot, Garage, GameObject, GridMaxRotation, MouseFollow, ObjectHover};
 
 pub fn object_mouse_follow(
     mut query: Query<&mut Transform, With<MouseFollow>>,
@@ -47,27 +47,29 @@ pub fn object_mouse_rotate(
 pub fn object_mouse_place_send(
     mut query: Query<(&GameObject, &AngularRot), With<MouseFollow>>,
     ms_input: Res<Input<MouseButton>>,
-    ms_pos: Res<MouseCubePos>,
+    hover: Res<HoverState>,
+    garage: Res<Garage>,
     mut client: ResMut<RenetClient>,
 ) {
     if ms_input.just_pressed(MouseButton::Left) {
+        // clicks off the board or on the padding between hexes place nothing
+        let at = match hover.coords {
+            Some(at) => at,
+            None => return,
+        };
         if let Ok((game_object, rotation)) = query.get_single_mut() {
-            let event = GameEvent::ShipPlaced {
-                player_id: client.client_id(),
-                at: ms_pos.0,
+            let command = GameCommand::ShipPlaced {
+                at,
                 rotation: rotation.0,
                 ship_type: game_object.clone(),
             };
-            client.send_message(0, bincode::serialize(&event).unwrap());
+            client.send_message(0, serde_json::to_vec(&command).unwrap());
 
-            // TODO! If garage == 0, attempt game start.
-            // BUG! BeginGame event must be fired only once.
-            // if there are none, attempt game start
-            None => {
-                let event = GameEvent::BeginGame {
-                    first_player: who_am_i.0,
-                };
-                client.send_message(0, bincode::serialize(&event).unwrap());
+            // if that was the last ship in the garage, commit the placement. The server begins
+            // the game once every player has committed.
+            if garage.0.is_empty() {
+                let command = GameCommand::PlacementCommitted;
+                client.send_message(0, serde_json::to_vec(&command).unwrap());
             }
         }
     }
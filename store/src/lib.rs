@@ -1,25 +1,70 @@
+pub mod ai;
 pub mod camera;
 pub mod game_objects;
 pub mod map;
 
 pub use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
-use game_objects::{GameObject, SHIPS};
-use map::components::CubeCoords;
+use game_objects::{get_max_grid_rotation, get_object_all_coords, Fleet, GameObject, PlacedShip};
+use map::{
+    components::{in_neutral_zone, is_contiguous, BoardSizeError, CubeCoords, HexMap},
+    HEX_CONFIG_BOARD_RADIUS,
+};
 
 /// Struct for storing player related data.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Player {
     pub name: String,
+    /// RGBA color the player's ships are drawn with.
+    #[serde(default = "Player::default_color")]
+    pub color: [f32; 4],
+    /// Team the player plays for, when [`GameState::team_mode`] is on.
+    #[serde(default)]
+    pub team: u8,
+}
+
+impl Player {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            color: Self::default_color(),
+            team: 0,
+        }
+    }
+
+    fn default_color() -> [f32; 4] {
+        [1.0, 1.0, 1.0, 1.0]
+    }
+}
+
+// colors are compared bit for bit, so players can be used as keys like the events holding them
+impl PartialEq for Player {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.color.map(f32::to_bits) == other.color.map(f32::to_bits)
+            && self.team == other.team
+    }
+}
+
+impl Eq for Player {}
+
+impl std::hash::Hash for Player {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.color.map(f32::to_bits).hash(state);
+        self.team.hash(state);
+    }
 }
 
 #[derive(Resource)]
 pub struct WhoAmI(pub PlayerId);
 
-/// An event that progresses the GameState forward
-#[derive(Debug, Clone, Serialize, PartialEq, Deserialize)]
+/// An event that progresses the GameState forward. Serialized with the variant name in a
+/// "type" field, next to the variant's own fields.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq, Hash, Deserialize)]
+#[serde(tag = "type")]
 pub enum GameEvent {
     SetupBoard,
     BeginGame {
@@ -35,6 +80,9 @@ pub enum GameEvent {
     PlayerDisconnected {
         player_id: PlayerId,
     },
+    PlayerReady {
+        player_id: PlayerId,
+    },
     // PlayerSelects {
     //     player_id: PlayerId,
     //     select_box: SelectQuad,
@@ -49,6 +97,108 @@ pub enum GameEvent {
         at: CubeCoords,
         rotation: i32,
     },
+    /// Places several ships at once, as (position, rotation) pairs. Ships are taken from the
+    /// player's garage in [`GameState::garage_order`].
+    PlaceMultiple {
+        player_id: PlayerId,
+        placements: Vec<(CubeCoords, u32)>,
+    },
+    Attack {
+        player_id: PlayerId,
+        at: CubeCoords,
+    },
+    PlacementCommitted {
+        player_id: PlayerId,
+    },
+    /// Takes back the last ship the player placed, returning it to their garage.
+    UndoPlacement {
+        player_id: PlayerId,
+    },
+    /// The player passes the rest of their turn.
+    EndTurn {
+        player_id: PlayerId,
+    },
+    /// The player wants a rematch of the game that just ended. The game goes back to the lobby
+    /// once every player asked for one.
+    RequestRematch {
+        player_id: PlayerId,
+    },
+}
+
+impl GameEvent {
+    /// The player an event is issued by, or None for events only the server issues.
+    pub fn player_id(&self) -> Option<PlayerId> {
+        use GameEvent::*;
+        match self {
+            SetupBoard | BeginGame { .. } | EndGame { .. } => None,
+            PlayerJoined { player_id, .. }
+            | PlayerDisconnected { player_id }
+            | PlayerReady { player_id }
+            | ShipMove { player_id, .. }
+            | ShipPlaced { player_id, .. }
+            | PlaceMultiple { player_id, .. }
+            | Attack { player_id, .. }
+            | PlacementCommitted { player_id }
+            | UndoPlacement { player_id }
+            | EndTurn { player_id }
+            | RequestRematch { player_id } => Some(*player_id),
+        }
+    }
+}
+
+/// What a player asks to do, before the server turns it into a [`GameEvent`] with
+/// [`GameState::resolve`]. The player issuing a command is whoever sent it.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq, Hash, Deserialize)]
+#[serde(tag = "type")]
+pub enum GameCommand {
+    PlayerReady,
+    ShipMove {
+        at: CubeCoords,
+    },
+    ShipPlaced {
+        ship_type: GameObject,
+        at: CubeCoords,
+        rotation: i32,
+    },
+    PlaceMultiple {
+        placements: Vec<(CubeCoords, u32)>,
+    },
+    Attack {
+        at: CubeCoords,
+    },
+    PlacementCommitted,
+    UndoPlacement,
+    EndTurn,
+    RequestRematch,
+}
+
+impl GameCommand {
+    /// The event this command stands for when `player_id` issues it.
+    pub fn into_event(self, player_id: PlayerId) -> GameEvent {
+        match self {
+            GameCommand::PlayerReady => GameEvent::PlayerReady { player_id },
+            GameCommand::ShipMove { at } => GameEvent::ShipMove { player_id, at },
+            GameCommand::ShipPlaced {
+                ship_type,
+                at,
+                rotation,
+            } => GameEvent::ShipPlaced {
+                player_id,
+                ship_type,
+                at,
+                rotation,
+            },
+            GameCommand::PlaceMultiple { placements } => GameEvent::PlaceMultiple {
+                player_id,
+                placements,
+            },
+            GameCommand::Attack { at } => GameEvent::Attack { player_id, at },
+            GameCommand::PlacementCommitted => GameEvent::PlacementCommitted { player_id },
+            GameCommand::UndoPlacement => GameEvent::UndoPlacement { player_id },
+            GameCommand::EndTurn => GameEvent::EndTurn { player_id },
+            GameCommand::RequestRematch => GameEvent::RequestRematch { player_id },
+        }
+    }
 }
 
 /// The different states a game can be in. (not to be confused with the entire "GameState")
@@ -61,27 +211,173 @@ pub enum GameStage {
     Ended,
 }
 
+impl GameStage {
+    /// Whether a game may go from stage `from` to stage `to`. Games go from Lobby through
+    /// PreGame and InGame to Ended, and back to Lobby for a rematch. InGame can be Paused and
+    /// resumed, and a game can end from any stage since players may leave at any time. Staying
+    /// in the same stage is always allowed.
+    pub fn can_transition(from: GameStage, to: GameStage) -> bool {
+        use GameStage::*;
+        from == to
+            || matches!(
+                (from, to),
+                (Lobby, PreGame)
+                    | (PreGame, InGame)
+                    | (InGame, Paused)
+                    | (Paused, InGame)
+                    | (_, Ended)
+                    | (Ended, Lobby)
+            )
+    }
+}
+
+/// How players take turns placing their ships during PreGame.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum PlacementMode {
+    /// Every player places their ships at the same time.
+    Simultaneous,
+    /// Players take turns placing one ship, or one batch of ships, at a time.
+    Alternating,
+}
+
+/// What a side has to lose for the game to end.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum WinCondition {
+    /// A side is out once every one of its ships is sunk.
+    SinkAll,
+    /// A side is out once its players' flagships are sunk, whatever other ships they have
+    /// left. See [`PlacedShip::is_flagship`].
+    SinkFlagship,
+}
+
+/// What [`GameState::apply_from`] does with an invalid event a player sent during the game.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum InvalidActionPolicy {
+    /// The event is rejected and the game goes on.
+    Reject,
+    /// The event is rejected and its sender forfeits, for tournament rules.
+    Forfeit,
+}
+
 /// This just makes it easier to dissern between a player id and any ol' u64
-type PlayerId = u64;
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, Default,
+)]
+#[serde(transparent)]
+pub struct PlayerId(pub u64);
+
+impl From<u64> for PlayerId {
+    fn from(id: u64) -> Self {
+        Self(id)
+    }
+}
+
+impl From<PlayerId> for u64 {
+    fn from(id: PlayerId) -> Self {
+        id.0
+    }
+}
+
+impl std::fmt::Display for PlayerId {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Who a player plays for, see [`GameState::side_of`]. Players on the same side don't fire at
+/// each other's ships and win or lose together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Side {
+    /// A team of [`GameState::team_mode`].
+    Team(u8),
+    /// A player playing for themselves.
+    Player(PlayerId),
+}
 
 /// A GameState object that is able to keep track of a game of TicTacTussle
+///
+/// Players take turns in ascending id order, wrapping from the highest id back to the lowest.
+/// Players that disconnected are skipped. In [`GameState::team_mode`] the teams take turns
+/// instead, each sending its players in ascending id order.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Resource)]
 pub struct GameState {
     pub stage: GameStage,
-    pub players: HashMap<PlayerId, Player>,
-    pub player_ships: HashMap<PlayerId, Vec<(GameObject, CubeCoords, i32)>>,
+    pub players: BTreeMap<PlayerId, Player>,
+    pub player_ships: BTreeMap<PlayerId, Vec<PlacedShip>>,
     pub history: Vec<GameEvent>,
     pub cur_player: Option<PlayerId>,
+    /// Coordinates each player has already fired upon.
+    pub player_shots: BTreeMap<PlayerId, BTreeSet<CubeCoords>>,
+    /// Players that have finished placing their ships, and are waiting for the game to begin.
+    pub committed_players: BTreeSet<PlayerId>,
+    /// Players that are ready for the board to be set up.
+    pub ready_players: BTreeSet<PlayerId>,
+    /// Games won by each player since they joined, kept across rematches.
+    pub series_score: BTreeMap<PlayerId, u32>,
+    /// Players that asked for a rematch since the game ended.
+    pub rematch_requests: BTreeSet<PlayerId>,
+    /// Radius of the hexagonal board, coordinates further away from the center are off it.
+    pub board_radius: u32,
+    /// Cells removed from the board. The HexMap of the board is built from them, see
+    /// [`GameState::hex_map`], rather than keeping holes of its own.
+    pub holes: BTreeSet<CubeCoords>,
+    /// Cells of the board covered by land. They stay on the board, but no ship may be placed
+    /// on them.
+    pub islands: BTreeSet<CubeCoords>,
+    /// Width of the neutral zone down the middle of the board, see
+    /// [`map::components::HexMap::with_neutral_zone`].
+    pub neutral_width: u32,
+    pub placement_mode: PlacementMode,
+    /// Ships every player places before the game begins.
+    pub fleet: Fleet,
+    /// Shuffles the order ships are taken from the garage in, see [`GameState::garage_order`].
+    pub shuffle_garage_seed: Option<u64>,
+    pub win_condition: WinCondition,
+    pub invalid_action_policy: InvalidActionPolicy,
+    /// Whether misses tell how close they were to a ship, see [`GameState::attack_result`].
+    pub reveal_proximity: bool,
+    /// Whether players play in the teams set in [`Player::team`]. Otherwise every player is on
+    /// their own.
+    pub team_mode: bool,
+    /// Moves and attacks a player can make before the turn passes.
+    pub actions_per_turn: u32,
+    /// Whether the player taking the second turn of the game gets an extra action in it, to
+    /// make up for not going first.
+    pub balance_second_player: bool,
+    /// Moves and attacks the current player made this turn.
+    pub actions_used: u32,
+    /// Turns that ended since the game began.
+    pub turns_played: u32,
 }
 
 impl Default for GameState {
     fn default() -> Self {
         Self {
             stage: GameStage::Lobby,
-            players: HashMap::new(),
-            player_ships: HashMap::new(),
+            players: BTreeMap::new(),
+            player_ships: BTreeMap::new(),
             history: Vec::new(),
             cur_player: None,
+            player_shots: BTreeMap::new(),
+            committed_players: BTreeSet::new(),
+            ready_players: BTreeSet::new(),
+            series_score: BTreeMap::new(),
+            rematch_requests: BTreeSet::new(),
+            board_radius: HEX_CONFIG_BOARD_RADIUS as u32,
+            holes: BTreeSet::new(),
+            islands: BTreeSet::new(),
+            neutral_width: 0,
+            placement_mode: PlacementMode::Simultaneous,
+            fleet: Fleet::standard(),
+            shuffle_garage_seed: None,
+            win_condition: WinCondition::SinkAll,
+            invalid_action_policy: InvalidActionPolicy::Reject,
+            reveal_proximity: false,
+            team_mode: false,
+            actions_per_turn: 1,
+            balance_second_player: false,
+            actions_used: 0,
+            turns_played: 0,
         }
     }
 }
@@ -89,27 +385,47 @@ impl Default for GameState {
 impl GameState {
     /// Determines whether an event is valid considering the current GameState
     pub fn validade(&self, event: &GameEvent) -> bool {
+        self.validate_detailed(event).is_ok()
+    }
+
+    /// Same as [`GameState::validade`], but tells why an invalid event was rejected.
+    pub fn validate_detailed(&self, event: &GameEvent) -> Result<(), RejectReason> {
         use GameEvent::*;
         match event {
             BeginGame { first_player } => {
                 if None == self.players.get(first_player) {
-                    return false;
+                    return Err(RejectReason::UnknownPlayer);
                 }
                 if self.players.len() != 2 {
-                    return false;
+                    return Err(RejectReason::NotEnoughPlayers);
+                }
+                if !self.all_players_ready() {
+                    return Err(RejectReason::PlayersNotReady);
+                }
+                // players stay ready and committed through the game, which begins only once
+                if self.stage != GameStage::PreGame {
+                    return Err(RejectReason::WrongStage);
                 }
                 if self
                     .player_ships
                     .iter()
-                    .any(|(_, vec)| vec.len() < SHIPS.len())
+                    .any(|(_, vec)| vec.len() < self.fleet.len())
+                {
+                    return Err(RejectReason::ShipsLeftToPlace);
+                }
+                if self
+                    .active_player_ids()
+                    .any(|p| !self.committed_players.contains(&p))
                 {
-                    return false;
+                    return Err(RejectReason::PlacementNotCommitted);
                 }
             }
             EndGame { reason } => match reason {
-                EndGameReason::PlayerWon { winner: _ } => {
+                EndGameReason::PlayerWon { .. }
+                | EndGameReason::TeamWon { .. }
+                | EndGameReason::Draw => {
                     if self.stage != GameStage::InGame {
-                        return false;
+                        return Err(RejectReason::WrongStage);
                     }
                 }
                 _ => {}
@@ -119,65 +435,314 @@ impl GameState {
                 player_details: _,
             } => {
                 if self.players.contains_key(player_id) {
-                    return false;
+                    return Err(RejectReason::PlayerAlreadyJoined);
                 }
             }
             PlayerDisconnected { player_id } => {
                 if !self.players.contains_key(player_id) {
-                    return false;
+                    return Err(RejectReason::UnknownPlayer);
+                }
+            }
+            PlayerReady { player_id } => {
+                if self.stage != GameStage::Lobby {
+                    return Err(RejectReason::WrongStage);
+                }
+                if !self.players.contains_key(player_id) {
+                    return Err(RejectReason::UnknownPlayer);
+                }
+                if self.ready_players.contains(player_id) {
+                    return Err(RejectReason::PlayerAlreadyReady);
                 }
             }
-            ShipMove { player_id, at: _ } => return self.is_player_turn(player_id),
-            ShipPlaced { player_id, .. } => {
+            ShipMove { player_id, at } => {
+                self.check_turn(player_id)?;
+                if self.stage != GameStage::InGame {
+                    return Err(RejectReason::WrongStage);
+                }
+                if !self.is_on_board(at) {
+                    return Err(RejectReason::OutOfBounds);
+                }
+            }
+            Attack { player_id, at } => {
+                self.check_turn(player_id)?;
+                if self.stage != GameStage::InGame {
+                    return Err(RejectReason::WrongStage);
+                }
+                if !self.is_on_board(at) {
+                    return Err(RejectReason::OutOfBounds);
+                }
+                // a coordinate can only be fired upon once per player, unless it still holds
+                // an armored ship cell that was hit but not destroyed
+                if let Some(shots) = self.player_shots.get(player_id) {
+                    if shots.contains(at) && !self.enemy_standing_at(player_id, at) {
+                        return Err(RejectReason::AlreadyAttacked);
+                    }
+                }
+            }
+            ShipPlaced {
+                player_id,
+                ship_type,
+                at,
+                rotation,
+            } => {
                 // check if game is in PreGame
                 if self.stage != GameStage::PreGame {
-                    return false;
+                    return Err(RejectReason::WrongStage);
+                }
+                if !self.is_placement_turn(player_id) {
+                    return Err(RejectReason::NotYourTurn);
                 }
 
-                // check if player is still allowed to place ships
+                // check if player is still allowed to place ships. More ships than the fleet
+                // holds would mean the garage went out of sync, which is rejected all the same
                 match self.player_ships.get(player_id) {
                     Some(garage) => {
-                        if garage.len() == SHIPS.len() {
-                            return false;
+                        if garage.len() >= self.fleet.len() {
+                            return Err(RejectReason::NoShipsLeft);
                         }
-                        if garage.len() > SHIPS.len() {
-                            panic!("{:?} has placed more ships than allowed", player_id);
+                        // ships are placed in garage order, whatever the player picks
+                        if self.garage_order().0[garage.len()] != *ship_type {
+                            return Err(RejectReason::WrongShipType);
                         }
                     }
                     None => {
-                        return false;
+                        return Err(RejectReason::UnknownPlayer);
+                    }
+                }
+
+                // the client wraps rotations either way around, see `object_mouse_rotate`
+                let max_rotation = get_max_grid_rotation(ship_type);
+                if !(-max_rotation..max_rotation).contains(rotation) {
+                    return Err(RejectReason::InvalidRotation);
+                }
+                // the origin is checked first, laying out a ship far off could overflow
+                if !self.is_on_board(at) {
+                    return Err(RejectReason::OutOfBounds);
+                }
+                let footprint = get_object_all_coords(ship_type, *rotation, at);
+                if !footprint.iter().all(|cell| self.is_on_board(cell)) {
+                    return Err(RejectReason::OutOfBounds);
+                }
+                if !is_contiguous(&footprint) {
+                    return Err(RejectReason::DisconnectedShip);
+                }
+                if footprint.iter().any(|cell| self.islands.contains(cell)) {
+                    return Err(RejectReason::BlockedTerrain);
+                }
+                if footprint.iter().any(|cell| self.is_neutral(cell)) {
+                    return Err(RejectReason::NeutralZone);
+                }
+                // the ships of a player can't share a cell, same as within a PlaceMultiple
+                let overlaps = self.player_ships[player_id]
+                    .iter()
+                    .flat_map(|ship| ship.cells.iter())
+                    .any(|(cell, _)| footprint.contains(cell));
+                if overlaps {
+                    return Err(RejectReason::ShipsOverlap);
+                }
+            }
+            PlaceMultiple {
+                player_id,
+                placements,
+            } => {
+                if self.stage != GameStage::PreGame {
+                    return Err(RejectReason::WrongStage);
+                }
+                if !self.is_placement_turn(player_id) {
+                    return Err(RejectReason::NotYourTurn);
+                }
+                let placed = self
+                    .player_ships
+                    .get(player_id)
+                    .ok_or(RejectReason::UnknownPlayer)?;
+                if placed.len() + placements.len() > self.fleet.len() {
+                    return Err(RejectReason::NoShipsLeft);
+                }
+
+                // the whole batch is rejected if any ship overlaps another, placed or not
+                let mut taken: HashSet<CubeCoords> = placed
+                    .iter()
+                    .flat_map(|ship| ship.cells.iter().map(|(cell, _)| *cell))
+                    .collect();
+                for (ship_type, (at, rotation)) in
+                    self.garage_order().0[placed.len()..].iter().zip(placements)
+                {
+                    if *rotation >= get_max_grid_rotation(ship_type) as u32 {
+                        return Err(RejectReason::InvalidRotation);
+                    }
+                    if !self.is_on_board(at) {
+                        return Err(RejectReason::OutOfBounds);
+                    }
+                    let ship = PlacedShip::new(*ship_type, *at, *rotation as i32);
+                    let footprint: Vec<CubeCoords> =
+                        ship.cells.iter().map(|(cell, _)| *cell).collect();
+                    if !footprint.iter().all(|cell| self.is_on_board(cell)) {
+                        return Err(RejectReason::OutOfBounds);
+                    }
+                    if !is_contiguous(&footprint) {
+                        return Err(RejectReason::DisconnectedShip);
+                    }
+                    for cell in footprint {
+                        if self.islands.contains(&cell) {
+                            return Err(RejectReason::BlockedTerrain);
+                        }
+                        if self.is_neutral(&cell) {
+                            return Err(RejectReason::NeutralZone);
+                        }
+                        if !taken.insert(cell) {
+                            return Err(RejectReason::ShipsOverlap);
+                        }
+                    }
+                }
+            }
+            PlacementCommitted { player_id } => {
+                if self.stage != GameStage::PreGame {
+                    return Err(RejectReason::WrongStage);
+                }
+                match self.player_ships.get(player_id) {
+                    Some(ships) => {
+                        if ships.len() < self.fleet.len() {
+                            return Err(RejectReason::ShipsLeftToPlace);
+                        }
+                    }
+                    None => return Err(RejectReason::UnknownPlayer),
+                }
+                if self.committed_players.contains(player_id) {
+                    return Err(RejectReason::PlacementAlreadyCommitted);
+                }
+            }
+            UndoPlacement { player_id } => {
+                if self.stage != GameStage::PreGame {
+                    return Err(RejectReason::WrongStage);
+                }
+                // taking ships back would get in the way of the turns to place them
+                if self.placement_mode != PlacementMode::Simultaneous {
+                    return Err(RejectReason::UndoNotAllowed);
+                }
+                match self.player_ships.get(player_id) {
+                    Some(ships) => {
+                        if ships.is_empty() {
+                            return Err(RejectReason::NothingToUndo);
+                        }
                     }
+                    None => return Err(RejectReason::UnknownPlayer),
+                }
+                if self.committed_players.contains(player_id) {
+                    return Err(RejectReason::PlacementAlreadyCommitted);
+                }
+            }
+            EndTurn { player_id } => {
+                self.check_turn(player_id)?;
+                if self.stage != GameStage::InGame {
+                    return Err(RejectReason::WrongStage);
+                }
+            }
+            RequestRematch { player_id } => {
+                if self.stage != GameStage::Ended {
+                    return Err(RejectReason::WrongStage);
+                }
+                if !self.players.contains_key(player_id) {
+                    return Err(RejectReason::UnknownPlayer);
+                }
+                if self.rematch_requests.contains(player_id) {
+                    return Err(RejectReason::RematchAlreadyRequested);
                 }
             }
             SetupBoard => {
                 if self.stage != GameStage::Lobby {
-                    return false;
+                    return Err(RejectReason::WrongStage);
                 }
                 if self.players.len() != 2 {
-                    return false;
+                    return Err(RejectReason::NotEnoughPlayers);
+                }
+                if !self.all_players_ready() {
+                    return Err(RejectReason::PlayersNotReady);
+                }
+                // the game would never leave PreGame if the fleet can't be placed
+                if self.board_cells() < self.fleet.total_cells() {
+                    return Err(RejectReason::BoardTooSmall);
                 }
             }
         }
-        true
+        Ok(())
+    }
+
+    /// Same as [`GameState::validade`] for an event received from `sender`, which must be the
+    /// player the event is issued by. Events only the server issues are always rejected.
+    pub fn validate_from(&self, event: &GameEvent, sender: PlayerId) -> bool {
+        event.player_id() == Some(sender) && self.validade(event)
+    }
+
+    /// The event a command from `sender` stands for, if it is valid in the current GameState.
+    pub fn resolve(&self, cmd: GameCommand, sender: PlayerId) -> Result<GameEvent, RejectReason> {
+        let event = cmd.into_event(sender);
+        self.validate_detailed(&event)?;
+        Ok(event)
+    }
+
+    /// Validates a sequence of events against the evolving GameState, consuming each valid
+    /// event on a scratch copy. Processing stops at the first invalid event, so it and every
+    /// event after it are reported as invalid.
+    pub fn validate_all(&self, events: &[GameEvent]) -> Vec<bool> {
+        let mut state = self.clone_for_simulation();
+        let mut results = Vec::with_capacity(events.len());
+        for event in events {
+            if !state.validade(event) {
+                break;
+            }
+            if state.consume(event).is_err() {
+                break;
+            }
+            results.push(true);
+        }
+        results.resize(events.len(), false);
+        results
     }
 
-    pub fn consume(&mut self, valid_event: &GameEvent) {
+    /// Progresses the GameState with an event that passed validation. This is the fast path for
+    /// callers that already ran [`GameState::validate_detailed`] or built the event themselves,
+    /// like the server: the rules are not checked again, so an unvalidated event may leave the
+    /// game in a state the rules forbid. Use [`GameState::apply`] for events from untrusted
+    /// sources.
+    ///
+    /// Never panics. Returns an error, leaving the GameState untouched, if the event turns out
+    /// not to apply after all: every check is made before anything is changed.
+    pub fn consume(&mut self, valid_event: &GameEvent) -> Result<(), ConsumeError> {
         use GameEvent::*;
+        let mut outcome = None;
+        let mut rematch = false;
         match valid_event {
             BeginGame { first_player } => {
                 let player = self
-                    .players
-                    .iter()
-                    .filter(|(p, _)| *p != first_player)
-                    .next()
-                    .unwrap();
-                self.cur_player = Some(*player.0);
-                trace!("First player: {:?}", *player.0);
-                self.stage = GameStage::InGame;
-            }
-            EndGame { reason: _ } => self.stage = GameStage::Ended,
+                    .active_player_ids()
+                    .find(|p| p != first_player)
+                    .ok_or(ConsumeError::NoOpponent(*first_player))?;
+                self.set_stage(GameStage::InGame)?;
+                self.cur_player = Some(player);
+                self.actions_used = 0;
+                self.turns_played = 0;
+                trace!("First player: {:?}", player);
+            }
+            EndGame { reason } => {
+                self.set_stage(GameStage::Ended)?;
+                let winners: Vec<PlayerId> = match reason {
+                    EndGameReason::PlayerWon { winner } => vec![*winner],
+                    EndGameReason::TeamWon { team } => self
+                        .active_player_ids()
+                        .filter(|p| self.players[p].team == *team)
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                for winner in winners {
+                    *self.series_score.entry(winner).or_insert(0) += 1;
+                }
+            }
             PlayerDisconnected { player_id } => {
                 self.players.remove(player_id);
+                self.ready_players.remove(player_id);
+            }
+            PlayerReady { player_id } => {
+                self.ready_players.insert(*player_id);
             }
             PlayerJoined {
                 player_id,
@@ -189,7 +754,27 @@ impl GameState {
                 player_id: _,
                 at: _,
             } => {
-                self.cur_player = self.next_player();
+                self.spend_action();
+            }
+            Attack { player_id, at } => {
+                // shots only damage the other sides, never the attacker's teammates
+                let side = self.side_of(player_id);
+                let enemies: Vec<PlayerId> = self
+                    .player_ships
+                    .keys()
+                    .copied()
+                    .filter(|p| self.side_of(p) != side)
+                    .collect();
+                for enemy in enemies {
+                    if let Some(ships) = self.player_ships.get_mut(&enemy) {
+                        for ship in ships.iter_mut() {
+                            ship.hit(at);
+                        }
+                    }
+                }
+                self.player_shots.entry(*player_id).or_default().insert(*at);
+                self.spend_action();
+                outcome = self.attack_outcome();
             }
             ShipPlaced {
                 player_id,
@@ -197,44 +782,2050 @@ impl GameState {
                 rotation,
                 ship_type,
             } => {
-                let ship_vec = self.player_ships.get_mut(&player_id).unwrap();
-                ship_vec.push((*ship_type, *at, *rotation));
+                let ship_vec = self
+                    .player_ships
+                    .get_mut(player_id)
+                    .ok_or(ConsumeError::NoGarage(*player_id))?;
+                if ship_vec.len() >= self.fleet.len() {
+                    return Err(ConsumeError::NoShipsLeft(*player_id));
+                }
+                let max_rotation = get_max_grid_rotation(ship_type);
+                if !(-max_rotation..max_rotation).contains(rotation) {
+                    return Err(ConsumeError::InvalidRotation(*player_id));
+                }
+                ship_vec.push(PlacedShip::new(*ship_type, *at, *rotation));
+                // the first ship a player places is their flagship
+                if let Some(flagship) = ship_vec.first_mut() {
+                    flagship.is_flagship = true;
+                }
+                self.pass_placement_turn();
+            }
+            PlaceMultiple {
+                player_id,
+                placements,
+            } => {
+                let garage = self.garage_order();
+                let ship_vec = self
+                    .player_ships
+                    .get_mut(player_id)
+                    .ok_or(ConsumeError::NoGarage(*player_id))?;
+                // placing only part of the batch would leave the garage half filled
+                if ship_vec.len() + placements.len() > garage.len() {
+                    return Err(ConsumeError::NoShipsLeft(*player_id));
+                }
+                if garage.0[ship_vec.len()..].iter().zip(placements).any(
+                    |(ship_type, (_, rotation))| {
+                        *rotation >= get_max_grid_rotation(ship_type) as u32
+                    },
+                ) {
+                    return Err(ConsumeError::InvalidRotation(*player_id));
+                }
+                let ships: Vec<PlacedShip> = garage.0[ship_vec.len()..]
+                    .iter()
+                    .zip(placements)
+                    .map(|(ship_type, (at, rotation))| {
+                        PlacedShip::new(*ship_type, *at, *rotation as i32)
+                    })
+                    .collect();
+                ship_vec.extend(ships);
+                if let Some(flagship) = ship_vec.first_mut() {
+                    flagship.is_flagship = true;
+                }
+                self.pass_placement_turn();
+            }
+            PlacementCommitted { player_id } => {
+                self.committed_players.insert(*player_id);
+            }
+            UndoPlacement { player_id } => {
+                self.player_ships
+                    .get_mut(player_id)
+                    .ok_or(ConsumeError::NoGarage(*player_id))?
+                    .pop();
+            }
+            EndTurn { player_id: _ } => {
+                self.end_turn();
+            }
+            RequestRematch { player_id } => {
+                self.rematch_requests.insert(*player_id);
+                rematch = self
+                    .active_player_ids()
+                    .all(|p| self.rematch_requests.contains(&p));
             }
             SetupBoard => {
-                self.stage = GameStage::PreGame;
-                for p in &self.players {
-                    self.player_ships.insert(*p.0, Vec::new());
+                self.set_stage(GameStage::PreGame)?;
+                if self.placement_mode == PlacementMode::Alternating {
+                    let first_player = self.active_player_ids().next();
+                    self.cur_player = first_player;
+                }
+                let player_ids: Vec<PlayerId> = self.active_player_ids().collect();
+                for p in player_ids {
+                    self.player_ships.insert(p, Vec::new());
                 }
             }
         }
 
         self.history.push(valid_event.clone());
+        if let Some(reason) = outcome {
+            self.consume(&EndGame { reason })?;
+        }
+        if rematch {
+            self.reset_to_lobby();
+        }
+        Ok(())
     }
 
-    fn next_player(&self) -> Option<PlayerId> {
-        if let Some(player_moved) = self.cur_player {
-            for (key, _) in self.players.iter() {
-                if player_moved != *key {
-                    return Some(*key);
+    /// Moves the game to `stage`, refusing transitions [`GameStage::can_transition`] forbids.
+    fn set_stage(&mut self, stage: GameStage) -> Result<(), ConsumeError> {
+        if !GameStage::can_transition(self.stage, stage) {
+            warn!(
+                "illegal stage transition from {:?} to {:?}",
+                self.stage, stage
+            );
+            return Err(ConsumeError::IllegalTransition {
+                from: self.stage,
+                to: stage,
+            });
+        }
+        self.stage = stage;
+        Ok(())
+    }
+
+    /// How the game ends after an attack, if it does: the last side with ships afloat wins, and
+    /// an attack sinking every remaining fleet at once leaves no winner.
+    fn attack_outcome(&self) -> Option<EndGameReason> {
+        let mut sides_afloat: Vec<Side> = self
+            .active_player_ids()
+            .filter(|p| !self.is_defeated(p))
+            .map(|p| self.side_of(&p))
+            .collect();
+        sides_afloat.sort();
+        sides_afloat.dedup();
+        match sides_afloat[..] {
+            [] => Some(EndGameReason::Draw),
+            [Side::Team(team)] => Some(EndGameReason::TeamWon { team }),
+            [Side::Player(winner)] => Some(EndGameReason::PlayerWon { winner }),
+            _ => None,
+        }
+    }
+
+    /// Ends the game in favor of the other side when `sender`, a player in it, sent
+    /// `invalid_event`. Does nothing outside of the game, for the joins and leaves only the
+    /// server issues, or when no single side is left to credit.
+    fn forfeit(&mut self, sender: PlayerId, invalid_event: &GameEvent) {
+        if self.stage != GameStage::InGame
+            || !self.players.contains_key(&sender)
+            || matches!(
+                invalid_event,
+                GameEvent::PlayerJoined { .. } | GameEvent::PlayerDisconnected { .. }
+            )
+        {
+            return;
+        }
+        let offender = self.side_of(&sender);
+        let mut sides_left: Vec<Side> = self
+            .active_player_ids()
+            .map(|p| self.side_of(&p))
+            .filter(|side| *side != offender)
+            .collect();
+        sides_left.sort();
+        sides_left.dedup();
+        let reason = match sides_left[..] {
+            [Side::Team(team)] => EndGameReason::TeamWon { team },
+            [Side::Player(winner)] => EndGameReason::PlayerWon { winner },
+            _ => return,
+        };
+        warn!("{} forfeits with {:?}", sender, invalid_event);
+        if let Err(err) = self.consume(&GameEvent::EndGame { reason }) {
+            warn!("Could not end the game: {}", err);
+        }
+    }
+
+    /// Places a copy of `from`'s ships for `to`, each one given a half turn around the center of
+    /// the board. Nothing is placed unless every mirrored ship can be.
+    pub fn mirror_placements(&mut self, from: PlayerId, to: PlayerId) -> Result<(), RejectReason> {
+        let events: Vec<GameEvent> = self
+            .player_ships
+            .get(&from)
+            .ok_or(RejectReason::UnknownPlayer)?
+            .iter()
+            .map(|ship| {
+                let max_rotation = get_max_grid_rotation(&ship.ship_type);
+                GameEvent::ShipPlaced {
+                    player_id: to,
+                    ship_type: ship.ship_type,
+                    at: ship.at.reflect_through(&CubeCoords::ZERO),
+                    rotation: (ship.rotation + max_rotation / 2).rem_euclid(max_rotation),
                 }
+            })
+            .collect();
+
+        let mut state = self.clone();
+        for event in &events {
+            state.validate_detailed(event)?;
+            state.consume(event)?;
+        }
+        *self = state;
+        Ok(())
+    }
+
+    /// Clone of the GameState without its history, for searches that clone it many times. The
+    /// clone validates and consumes events like the original, but anything reading the history,
+    /// such as [`GameState::attacks`], only sees the events consumed after cloning.
+    pub fn clone_for_simulation(&self) -> GameState {
+        GameState {
+            stage: self.stage,
+            players: self.players.clone(),
+            player_ships: self.player_ships.clone(),
+            history: Vec::new(),
+            cur_player: self.cur_player,
+            player_shots: self.player_shots.clone(),
+            committed_players: self.committed_players.clone(),
+            ready_players: self.ready_players.clone(),
+            series_score: self.series_score.clone(),
+            rematch_requests: self.rematch_requests.clone(),
+            board_radius: self.board_radius,
+            holes: self.holes.clone(),
+            islands: self.islands.clone(),
+            neutral_width: self.neutral_width,
+            placement_mode: self.placement_mode,
+            fleet: self.fleet.clone(),
+            shuffle_garage_seed: self.shuffle_garage_seed,
+            win_condition: self.win_condition,
+            invalid_action_policy: self.invalid_action_policy,
+            reveal_proximity: self.reveal_proximity,
+            team_mode: self.team_mode,
+            actions_per_turn: self.actions_per_turn,
+            balance_second_player: self.balance_second_player,
+            actions_used: self.actions_used,
+            turns_played: self.turns_played,
+        }
+    }
+
+    /// The GameState as it was after the first `index` events of the history, rebuilt by
+    /// consuming them again on a GameState with the same settings. Players that were already
+    /// there when the history began, like after [`GameState::reset_to_lobby`], are there from
+    /// the start. An `index` past the end of the history gives the current GameState.
+    pub fn state_at(&self, index: usize) -> GameState {
+        if index >= self.history.len() {
+            return self.clone();
+        }
+        self.replay(index)
+    }
+
+    /// Rebuilds the GameState from the first `index` events of the history, see
+    /// [`GameState::state_at`].
+    fn replay(&self, index: usize) -> GameState {
+        let mut state = GameState {
+            board_radius: self.board_radius,
+            holes: self.holes.clone(),
+            islands: self.islands.clone(),
+            neutral_width: self.neutral_width,
+            placement_mode: self.placement_mode,
+            fleet: self.fleet.clone(),
+            shuffle_garage_seed: self.shuffle_garage_seed,
+            win_condition: self.win_condition,
+            invalid_action_policy: self.invalid_action_policy,
+            reveal_proximity: self.reveal_proximity,
+            team_mode: self.team_mode,
+            actions_per_turn: self.actions_per_turn,
+            balance_second_player: self.balance_second_player,
+            ..GameState::default()
+        };
+        state.players = self
+            .players
+            .iter()
+            .filter(|(player_id, _)| {
+                !self.history.iter().any(|event| {
+                    matches!(event, GameEvent::PlayerJoined { player_id: p, .. } if p == *player_id)
+                })
+            })
+            .map(|(player_id, player)| (*player_id, player.clone()))
+            .collect();
+        // consuming an event can push more to the history, like the end of the game after the
+        // last attack, so those are skipped rather than consumed twice
+        while state.history.len() < index {
+            let event = &self.history[state.history.len()];
+            if let Err(err) = state.consume(event) {
+                warn!("Could not replay {:?}: {}", event, err);
+                break;
             }
         }
-        None
+        state
     }
 
-    fn is_player_turn(&self, player_id: &PlayerId) -> bool {
-        if let Some(p) = self.cur_player {
-            if *player_id == p {
-                return true;
+    /// Panics if the GameState differs from the one rebuilt by consuming its history again,
+    /// which means `consume` updated it wrongly along the way. Only checks in debug builds.
+    pub fn debug_assert_consistent(&self) {
+        #[cfg(debug_assertions)]
+        {
+            let replayed = self.replay(self.history.len());
+            debug_assert_eq!(self.history, replayed.history, "history");
+            debug_assert_eq!(self.stage, replayed.stage, "stage");
+            debug_assert_eq!(self.players, replayed.players, "players");
+            debug_assert_eq!(self.player_ships, replayed.player_ships, "player ships");
+            debug_assert_eq!(self.cur_player, replayed.cur_player, "current player");
+            debug_assert_eq!(self.player_shots, replayed.player_shots, "player shots");
+            debug_assert_eq!(
+                self.committed_players, replayed.committed_players,
+                "committed players"
+            );
+            debug_assert_eq!(self.ready_players, replayed.ready_players, "ready players");
+            debug_assert_eq!(
+                self.rematch_requests, replayed.rematch_requests,
+                "rematch requests"
+            );
+            debug_assert_eq!(self.actions_used, replayed.actions_used, "actions used");
+            debug_assert_eq!(self.turns_played, replayed.turns_played, "turns played");
+        }
+    }
+
+    /// Serializes the GameState to JSON, e.g. to save a game.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
+    /// Serializes the GameState to JSON without its history, e.g. to catch up a spectator
+    /// joining late in a long game. The board, stage and turn are all there, but the loaded
+    /// GameState can't go back through [`GameState::state_at`].
+    pub fn to_compact_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(&self.clone_for_simulation())
+    }
+
+    /// Loads a GameState saved with [`GameState::to_json`] or [`GameState::to_compact_json`],
+    /// refusing one that deserializes but could not have been reached by consuming events.
+    pub fn from_json(s: &str) -> Result<GameState, LoadStateError> {
+        let state: GameState = serde_json::from_str(s).map_err(LoadStateError::Json)?;
+        state.check_consistency()?;
+        Ok(state)
+    }
+
+    fn check_consistency(&self) -> Result<(), LoadStateError> {
+        if self.stage != GameStage::Ended {
+            if let Some(player_id) = self.cur_player {
+                if !self.players.contains_key(&player_id) {
+                    return Err(LoadStateError::UnknownPlayer(player_id));
+                }
             }
         }
-        false
+        if let Some(player_id) = self
+            .ready_players
+            .iter()
+            .find(|p| !self.players.contains_key(p))
+        {
+            return Err(LoadStateError::UnknownPlayer(*player_id));
+        }
+        if matches!(self.stage, GameStage::PreGame | GameStage::InGame) {
+            if let Some(player_id) = self
+                .active_player_ids()
+                .find(|p| !self.player_ships.contains_key(p))
+            {
+                return Err(LoadStateError::NoGarage(player_id));
+            }
+        }
+        if let Some((player_id, _)) = self
+            .player_ships
+            .iter()
+            .find(|(_, ships)| ships.len() > self.fleet.len())
+        {
+            return Err(LoadStateError::TooManyShips(*player_id));
+        }
+        Ok(())
     }
-}
 
-/// The various reasons why a game could end
-#[derive(Debug, Clone, Copy, Serialize, PartialEq, Deserialize)]
-pub enum EndGameReason {
-    PlayerLeft { player_id: PlayerId },
-    PlayerWon { winner: PlayerId },
+    /// Validates an event and consumes it if it is valid, leaving the GameState untouched
+    /// otherwise. An event that fails to apply after validation is rejected too, with the
+    /// closest matching reason. Costs a validation more than [`GameState::consume`], and tells
+    /// what the event did in return, see [`EventOutcome`].
+    ///
+    /// Rejected events are never held against a player, since there is no telling who sent
+    /// them. See [`GameState::apply_from`] for events received from a player.
+    pub fn apply(&mut self, event: GameEvent) -> Result<EventOutcome, RejectReason> {
+        self.validate_detailed(&event)?;
+        self.consume(&event)?;
+        let outcome = match event {
+            GameEvent::Attack { player_id, at } => self
+                .attack_result(player_id, at)
+                .map_or(EventOutcome::Applied, EventOutcome::Attack),
+            _ => EventOutcome::Applied,
+        };
+        Ok(outcome)
+    }
+
+    /// Same as [`GameState::apply`] for an event received from `sender`, which must be the
+    /// player the event is issued by. Under [`InvalidActionPolicy::Forfeit`], `sender` also
+    /// loses the game to the other side when the event is rejected during the game, whoever
+    /// the event names.
+    pub fn apply_from(
+        &mut self,
+        event: GameEvent,
+        sender: PlayerId,
+    ) -> Result<EventOutcome, RejectReason> {
+        let result = if event.player_id() == Some(sender) {
+            self.apply(event.clone())
+        } else {
+            Err(RejectReason::WrongSender)
+        };
+        if result.is_err() && self.invalid_action_policy == InvalidActionPolicy::Forfeit {
+            self.forfeit(sender, &event);
+        }
+        result
+    }
+
+    /// Everything an end game screen shows about the game, gathered from the history and the
+    /// ships on the board.
+    pub fn summary(&self) -> GameSummary {
+        let reason = self.history.iter().rev().find_map(|event| match event {
+            GameEvent::EndGame { reason } => Some(*reason),
+            _ => None,
+        });
+        let winner = match reason {
+            Some(EndGameReason::PlayerWon { winner }) => Some(winner),
+            _ => None,
+        };
+        let players = self
+            .active_player_ids()
+            .map(|player_id| {
+                let attacks: Vec<&CubeCoords> = self
+                    .attacks()
+                    .into_iter()
+                    .filter_map(|event| match event {
+                        GameEvent::Attack { player_id: p, at } if *p == player_id => Some(at),
+                        _ => None,
+                    })
+                    .collect();
+                // a cell can only be attacked again while it still holds a ship, so every
+                // attack on a ship cell was a hit
+                let hits = attacks
+                    .iter()
+                    .filter(|at| {
+                        self.enemy_ships(&player_id)
+                            .any(|ship| ship.cells.iter().any(|(cell, _)| cell == **at))
+                    })
+                    .count() as u32;
+                let shots = attacks.len() as u32;
+                PlayerSummary {
+                    player_id,
+                    shots,
+                    hits,
+                    accuracy: if shots == 0 {
+                        0.0
+                    } else {
+                        hits as f32 / shots as f32
+                    },
+                    ships_lost: self.player_ships.get(&player_id).map_or(0, |ships| {
+                        ships.iter().filter(|ship| ship.is_sunk()).count() as u32
+                    }),
+                }
+            })
+            .collect();
+        GameSummary {
+            winner,
+            reason,
+            // a turn cut short by the end of the game still counts
+            turns: self.turns_played + (self.actions_used > 0) as u32,
+            players,
+        }
+    }
+
+    /// Brings an ended game back to the Lobby for a rematch, keeping the players and their
+    /// series score. Returns false, leaving the GameState untouched, unless the game has ended.
+    pub fn reset_to_lobby(&mut self) -> bool {
+        if self.stage != GameStage::Ended {
+            return false;
+        }
+        self.stage = GameStage::Lobby;
+        self.player_ships.clear();
+        self.player_shots.clear();
+        self.history.clear();
+        self.cur_player = None;
+        self.actions_used = 0;
+        self.turns_played = 0;
+        self.committed_players.clear();
+        self.ready_players.clear();
+        self.rematch_requests.clear();
+        true
+    }
+
+    /// Whether a ship of any player on another side than `player_id` has a standing cell at
+    /// `at`.
+    fn enemy_standing_at(&self, player_id: &PlayerId, at: &CubeCoords) -> bool {
+        self.enemy_ships(player_id)
+            .any(|ship| ship.is_standing_at(at))
+    }
+
+    /// What became of the shots `by` fired at `at`, as the board stands now. None if they
+    /// never fired there.
+    pub fn attack_result(&self, by: PlayerId, at: CubeCoords) -> Option<AttackOutcome> {
+        if !self.player_shots.get(&by)?.contains(&at) {
+            return None;
+        }
+        let ships_hit: Vec<&PlacedShip> = self
+            .enemy_ships(&by)
+            .filter(|ship| ship.cells.iter().any(|(cell, _)| *cell == at))
+            .collect();
+        if ships_hit.is_empty() {
+            let distance = if self.reveal_proximity {
+                self.enemy_ships(&by)
+                    .filter(|ship| !ship.is_sunk())
+                    .flat_map(|ship| ship.cells.iter().map(|(cell, _)| cell.distance(&at)))
+                    .min()
+            } else {
+                None
+            };
+            Some(AttackOutcome::Miss { distance })
+        } else if let Some(ship) = ships_hit.iter().find(|ship| ship.is_sunk()) {
+            Some(AttackOutcome::Sunk {
+                ship: ship.ship_type,
+            })
+        } else {
+            Some(AttackOutcome::Hit {
+                ship: ships_hit[0].ship_type,
+            })
+        }
+    }
+
+    /// Whether `coords` lies within [`GameState::board_radius`], and isn't one of the holes.
+    /// Coordinates off the q + r + s = 0 plane are never on the board.
+    pub fn is_on_board(&self, coords: &CubeCoords) -> bool {
+        // widened so that coordinates from untrusted events can't overflow, which computing
+        // their magnitude could
+        let (q, r, s) = (coords.q as i64, coords.r as i64, coords.s as i64);
+        let radius = self.board_radius as i64;
+        q + r + s == 0
+            && [q, r, s].iter().all(|c| c.abs() <= radius)
+            && !self.holes.contains(coords)
+    }
+
+    /// The HexMap of the board these rules are played on: [`GameState::board_radius`] without
+    /// the holes, and with the neutral zone marked.
+    pub fn hex_map(&self, hex_size: f32, padding: f32) -> Result<HexMap, BoardSizeError> {
+        Ok(
+            HexMap::new_from_axial(self.board_radius as i32, hex_size, padding)?
+                .without_cells(self.holes.iter().copied())
+                .with_neutral_zone(self.neutral_width),
+        )
+    }
+
+    /// Number of cells ships can be placed on: those of a board of [`GameState::board_radius`]
+    /// that are neither holes, islands nor in the neutral zone.
+    pub fn board_cells(&self) -> u32 {
+        CubeCoords::ZERO
+            .range(self.board_radius)
+            .iter()
+            .filter(|coords| {
+                self.is_on_board(coords)
+                    && !self.islands.contains(coords)
+                    && !self.is_neutral(coords)
+            })
+            .count() as u32
+    }
+
+    /// The ship covering each cell `player_id` has placed a ship on. Empty before they place
+    /// any ship.
+    pub fn placement_grid(&self, player_id: &PlayerId) -> HashMap<CubeCoords, GameObject> {
+        self.player_ships
+            .get(player_id)
+            .into_iter()
+            .flatten()
+            .flat_map(|ship| ship.cells.iter().map(|(cell, _)| (*cell, ship.ship_type)))
+            .collect()
+    }
+
+    /// Number of ships `player_id` has placed that aren't sunk yet.
+    pub fn remaining_ships_count(&self, player_id: &PlayerId) -> usize {
+        self.player_ships.get(player_id).map_or(0, |ships| {
+            ships.iter().filter(|ship| !ship.is_sunk()).count()
+        })
+    }
+
+    /// Whether every ship `player_id` has placed is sunk.
+    pub fn is_fleet_sunk(&self, player_id: &PlayerId) -> bool {
+        self.player_ships
+            .get(player_id)
+            .is_some_and(|ships| !ships.is_empty() && ships.iter().all(PlacedShip::is_sunk))
+    }
+
+    /// The order every player takes ships from their garage in: the [`GameState::fleet`] order,
+    /// shuffled by [`GameState::shuffle_garage_seed`] if one is set.
+    pub fn garage_order(&self) -> Fleet {
+        match self.shuffle_garage_seed {
+            Some(seed) => self.fleet.shuffled(seed),
+            None => self.fleet.clone(),
+        }
+    }
+
+    /// Whether the flagship of `player_id` is sunk. False until they place a ship.
+    pub fn is_flagship_sunk(&self, player_id: &PlayerId) -> bool {
+        self.player_ships
+            .get(player_id)
+            .is_some_and(|ships| ships.iter().any(|ship| ship.is_flagship && ship.is_sunk()))
+    }
+
+    /// Whether `player_id` lost what [`GameState::win_condition`] asks them to keep afloat.
+    fn is_defeated(&self, player_id: &PlayerId) -> bool {
+        match self.win_condition {
+            WinCondition::SinkAll => self.is_fleet_sunk(player_id),
+            WinCondition::SinkFlagship => self.is_flagship_sunk(player_id),
+        }
+    }
+
+    /// Whether `coords` lies in the neutral zone ships can't be placed on.
+    pub fn is_neutral(&self, coords: &CubeCoords) -> bool {
+        in_neutral_zone(coords, &CubeCoords::ZERO, self.neutral_width)
+    }
+
+    fn all_players_ready(&self) -> bool {
+        self.active_player_ids()
+            .all(|player_id| self.ready_players.contains(&player_id))
+    }
+
+    /// Ids of the players taking part in the game, in ascending order. Players that
+    /// disconnected are left out.
+    pub fn active_player_ids(&self) -> impl Iterator<Item = PlayerId> + '_ {
+        self.players.keys().copied()
+    }
+
+    /// Every player with their details, in ascending id order. This is the order lobbies and
+    /// scoreboards list players in, and the order turns go in outside of
+    /// [`GameState::team_mode`].
+    pub fn players_sorted(&self) -> Vec<(PlayerId, &Player)> {
+        self.players
+            .iter()
+            .map(|(player_id, player)| (*player_id, player))
+            .collect()
+    }
+
+    /// Events from the history matching the given predicate, oldest first.
+    pub fn events_of<F: Fn(&GameEvent) -> bool>(&self, pred: F) -> Vec<&GameEvent> {
+        self.history.iter().filter(|event| pred(event)).collect()
+    }
+
+    /// The most recent event of the history, None before anything happened.
+    pub fn last_event(&self) -> Option<&GameEvent> {
+        self.history.last()
+    }
+
+    /// The most recent event of the history matching the given predicate.
+    pub fn last_event_of<F: Fn(&GameEvent) -> bool>(&self, pred: F) -> Option<&GameEvent> {
+        self.history.iter().rev().find(|event| pred(event))
+    }
+
+    pub fn attacks(&self) -> Vec<&GameEvent> {
+        self.events_of(|event| matches!(event, GameEvent::Attack { .. }))
+    }
+
+    pub fn placements(&self) -> Vec<&GameEvent> {
+        self.events_of(|event| {
+            matches!(
+                event,
+                GameEvent::ShipPlaced { .. } | GameEvent::PlaceMultiple { .. }
+            )
+        })
+    }
+
+    pub fn moves(&self) -> Vec<&GameEvent> {
+        self.events_of(|event| matches!(event, GameEvent::ShipMove { .. }))
+    }
+
+    /// Events from the history issued by `player_id`, or attacks on a cell one of their ships
+    /// covers. Events only the server issues, like [`GameEvent::BeginGame`], are included when
+    /// `include_server_events` is set.
+    pub fn events_for_player(
+        &self,
+        player_id: &PlayerId,
+        include_server_events: bool,
+    ) -> Vec<&GameEvent> {
+        let covered = |at: &CubeCoords| {
+            self.player_ships.get(player_id).is_some_and(|ships| {
+                ships
+                    .iter()
+                    .any(|ship| ship.cells.iter().any(|(cell, _)| cell == at))
+            })
+        };
+        self.events_of(|event| match event {
+            GameEvent::Attack {
+                player_id: attacker,
+                at,
+            } => attacker == player_id || covered(at),
+            _ => event
+                .player_id()
+                .map_or(include_server_events, |issuer| issuer == *player_id),
+        })
+    }
+
+    /// Whether `player_id` can act right now: on their turn during the game, or while they
+    /// still have ships left to place before it.
+    pub fn can_player_act(&self, player_id: &PlayerId) -> bool {
+        match self.stage {
+            GameStage::PreGame => {
+                self.has_ships_left(player_id) && self.is_placement_turn(player_id)
+            }
+            GameStage::InGame => self.is_player_turn(player_id),
+            _ => false,
+        }
+    }
+
+    /// Cells `player_id` may attack right now, empty unless it's their turn during the game.
+    pub fn attackable_cells(&self, player_id: &PlayerId) -> HashSet<CubeCoords> {
+        let radius = self.board_radius as i32;
+        (-radius..=radius)
+            .flat_map(|q| (-radius..=radius).map(move |r| CubeCoords { q, r, s: -q - r }))
+            .filter(|at| {
+                self.validade(&GameEvent::Attack {
+                    player_id: *player_id,
+                    at: *at,
+                })
+            })
+            .collect()
+    }
+
+    /// The origin closest to `near` where `player_id` could place their next ship with the
+    /// given rotation: on the board, clear of the neutral zone and of their other ships. Rings
+    /// around `near` are searched outward, so `near` itself is returned when it is already
+    /// legal. None if the ship fits nowhere, or if the player can't place a ship right now.
+    pub fn suggest_placement(
+        &self,
+        player_id: &PlayerId,
+        near: CubeCoords,
+        rotation: u32,
+    ) -> Option<CubeCoords> {
+        // the farthest any cell of the board can be from `near`
+        let max_radius = near.magnitude() + self.board_radius;
+        let mut ring = Vec::new();
+        for radius in 0..=max_radius {
+            near.ring_into(radius, &mut ring);
+            let found = ring.iter().copied().find(|at| {
+                self.validade(&GameEvent::PlaceMultiple {
+                    player_id: *player_id,
+                    placements: vec![(*at, rotation)],
+                })
+            });
+            if found.is_some() {
+                return found;
+            }
+        }
+        None
+    }
+
+    /// The player whose turn comes after the current one, or None outside of InGame.
+    pub fn next_player_id(&self) -> Option<PlayerId> {
+        if self.stage != GameStage::InGame {
+            return None;
+        }
+        self.next_player()
+    }
+
+    fn has_ships_left(&self, player_id: &PlayerId) -> bool {
+        self.player_ships
+            .get(player_id)
+            .is_some_and(|ships| ships.len() < self.fleet.len())
+    }
+
+    /// Whether `player_id` may place ships now, which is always the case unless placement
+    /// is [`PlacementMode::Alternating`].
+    fn is_placement_turn(&self, player_id: &PlayerId) -> bool {
+        match self.placement_mode {
+            PlacementMode::Simultaneous => true,
+            PlacementMode::Alternating => self.is_player_turn(player_id),
+        }
+    }
+
+    /// In [`PlacementMode::Alternating`], hands placement over to the next player with ships
+    /// left to place, or back to the current one if they are the only one left.
+    fn pass_placement_turn(&mut self) {
+        if self.placement_mode != PlacementMode::Alternating {
+            return;
+        }
+        let player_ids: Vec<PlayerId> = self.active_player_ids().collect();
+        let current = self
+            .cur_player
+            .and_then(|p| player_ids.iter().position(|id| *id == p))
+            .unwrap_or(0);
+        self.cur_player = (1..=player_ids.len())
+            .map(|i| player_ids[(current + i) % player_ids.len()])
+            .find(|p| self.has_ships_left(p));
+    }
+
+    /// Counts a move or attack against the current player's budget, passing the turn once it
+    /// is spent.
+    fn spend_action(&mut self) {
+        self.actions_used += 1;
+        if self.actions_used >= self.actions_this_turn() {
+            self.end_turn();
+        }
+    }
+
+    /// Moves and attacks the current player can make this turn, see
+    /// [`GameState::balance_second_player`].
+    fn actions_this_turn(&self) -> u32 {
+        let second_turn = self.turns_played == 1;
+        self.actions_per_turn + (self.balance_second_player && second_turn) as u32
+    }
+
+    fn end_turn(&mut self) {
+        self.cur_player = self.next_player();
+        self.actions_used = 0;
+        self.turns_played += 1;
+    }
+
+    /// The side a player plays for: their team in [`GameState::team_mode`], or themselves.
+    pub fn side_of(&self, player_id: &PlayerId) -> Side {
+        match self.players.get(player_id) {
+            Some(player) if self.team_mode => Side::Team(player.team),
+            _ => Side::Player(*player_id),
+        }
+    }
+
+    /// Ships of every player on another side than `player_id`, the ones they fire at.
+    pub fn enemy_ships(&self, player_id: &PlayerId) -> impl Iterator<Item = &PlacedShip> + '_ {
+        let side = self.side_of(player_id);
+        self.player_ships
+            .iter()
+            .filter(move |(p, _)| self.side_of(p) != side)
+            .flat_map(|(_, ships)| ships)
+    }
+
+    /// Order in which players take turns: the first player of each side in turn, then the
+    /// second, and so on, so sides alternate. Sides and their players go by ascending id.
+    fn turn_order(&self) -> Vec<PlayerId> {
+        let mut sides: Vec<(Side, Vec<PlayerId>)> = Vec::new();
+        for player_id in self.active_player_ids() {
+            let side = self.side_of(&player_id);
+            match sides.iter_mut().find(|(s, _)| *s == side) {
+                Some((_, members)) => members.push(player_id),
+                None => sides.push((side, vec![player_id])),
+            }
+        }
+        sides.sort_by_key(|(side, _)| *side);
+        let rounds = sides.iter().map(|(_, members)| members.len()).max();
+        (0..rounds.unwrap_or(0))
+            .flat_map(|i| {
+                sides
+                    .iter()
+                    .filter_map(move |(_, members)| members.get(i).copied())
+            })
+            .collect()
+    }
+
+    fn next_player(&self) -> Option<PlayerId> {
+        let player_moved = self.cur_player?;
+        let order = self.turn_order();
+        let position = order.iter().position(|p| *p == player_moved);
+        match position {
+            Some(i) => Some(order[(i + 1) % order.len()]).filter(|p| *p != player_moved),
+            // the player who moved disconnected, the turn goes to whoever came after them
+            None => order
+                .iter()
+                .copied()
+                .find(|p| *p > player_moved)
+                .or_else(|| order.first().copied()),
+        }
+    }
+
+    /// Rejects turn based events while no turn is in progress, or from anyone but the player
+    /// whose turn it is.
+    fn check_turn(&self, player_id: &PlayerId) -> Result<(), RejectReason> {
+        match self.cur_player {
+            None => Err(RejectReason::GameNotStarted),
+            Some(p) if p == *player_id => Ok(()),
+            Some(_) => Err(RejectReason::NotYourTurn),
+        }
+    }
+
+    fn is_player_turn(&self, player_id: &PlayerId) -> bool {
+        if let Some(p) = self.cur_player {
+            if *player_id == p {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// The various reasons why an event could be rejected by [`GameState::validate_detailed`]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum RejectReason {
+    UnknownPlayer,
+    PlayerAlreadyJoined,
+    PlayerAlreadyReady,
+    RematchAlreadyRequested,
+    PlayersNotReady,
+    NotEnoughPlayers,
+    WrongStage,
+    /// A turn based event arrived while no turn is in progress.
+    GameNotStarted,
+    NotYourTurn,
+    NoShipsLeft,
+    /// The placed ship is not the next one in [`GameState::garage_order`].
+    WrongShipType,
+    ShipsLeftToPlace,
+    PlacementNotCommitted,
+    PlacementAlreadyCommitted,
+    /// Ships can only be taken back while players place them simultaneously.
+    UndoNotAllowed,
+    NothingToUndo,
+    AlreadyAttacked,
+    InvalidRotation,
+    ShipsOverlap,
+    OutOfBounds,
+    BoardTooSmall,
+    NeutralZone,
+    DisconnectedShip,
+    /// A ship would cover one of the [`GameState::islands`].
+    BlockedTerrain,
+    /// The event is issued by another player than the one who sent it.
+    WrongSender,
+}
+
+/// The various reasons why [`GameState::consume`] could fail to apply an event
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ConsumeError {
+    /// The game can't begin without a player other than the given one.
+    NoOpponent(PlayerId),
+    /// The player has no garage to place ships from.
+    NoGarage(PlayerId),
+    /// The player's garage doesn't hold every ship being placed.
+    NoShipsLeft(PlayerId),
+    /// A ship the player places is rotated further than a full turn.
+    InvalidRotation(PlayerId),
+    /// The event would move the game to a stage it can't reach from the current one.
+    IllegalTransition { from: GameStage, to: GameStage },
+}
+
+impl std::fmt::Display for ConsumeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ConsumeError::NoOpponent(player_id) => {
+                write!(f, "no opponent for player {}", player_id)
+            }
+            ConsumeError::NoGarage(player_id) => write!(f, "no garage for player {}", player_id),
+            ConsumeError::NoShipsLeft(player_id) => {
+                write!(f, "not enough ships left for player {}", player_id)
+            }
+            ConsumeError::InvalidRotation(player_id) => {
+                write!(f, "invalid ship rotation from player {}", player_id)
+            }
+            ConsumeError::IllegalTransition { from, to } => {
+                write!(f, "can't go from {:?} to {:?}", from, to)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConsumeError {}
+
+/// The closest reason to reject an event with, when it passed validation but could not be
+/// consumed after all.
+impl From<ConsumeError> for RejectReason {
+    fn from(err: ConsumeError) -> Self {
+        match err {
+            ConsumeError::NoOpponent(_) => RejectReason::NotEnoughPlayers,
+            ConsumeError::NoGarage(_) => RejectReason::UnknownPlayer,
+            ConsumeError::NoShipsLeft(_) => RejectReason::NoShipsLeft,
+            ConsumeError::InvalidRotation(_) => RejectReason::InvalidRotation,
+            ConsumeError::IllegalTransition { .. } => RejectReason::WrongStage,
+        }
+    }
+}
+
+/// The various reasons why [`GameState::from_json`] could fail to load a GameState
+#[derive(Debug)]
+pub enum LoadStateError {
+    Json(serde_json::Error),
+    /// The player is referenced by the GameState without having joined.
+    UnknownPlayer(PlayerId),
+    /// The player takes part in the game without a garage.
+    NoGarage(PlayerId),
+    /// The player has placed more ships than the fleet holds.
+    TooManyShips(PlayerId),
+}
+
+impl std::fmt::Display for LoadStateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LoadStateError::Json(err) => write!(f, "invalid game state json: {}", err),
+            LoadStateError::UnknownPlayer(player_id) => write!(f, "unknown player {}", player_id),
+            LoadStateError::NoGarage(player_id) => write!(f, "no garage for player {}", player_id),
+            LoadStateError::TooManyShips(player_id) => {
+                write!(f, "player {} placed too many ships", player_id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadStateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadStateError::Json(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// What [`GameState::summary`] reports about a game.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameSummary {
+    pub winner: Option<PlayerId>,
+    /// Why the game ended, None while it is still going.
+    pub reason: Option<EndGameReason>,
+    pub turns: u32,
+    /// Stats of each player, in ascending id order.
+    pub players: Vec<PlayerSummary>,
+}
+
+/// The stats of one player in a [`GameSummary`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerSummary {
+    pub player_id: PlayerId,
+    pub shots: u32,
+    pub hits: u32,
+    /// Share of shots that hit, between 0 and 1.
+    pub accuracy: f32,
+    pub ships_lost: u32,
+}
+
+/// What became of a cell a player fired upon, see [`GameState::attack_result`].
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, Hash, Deserialize)]
+pub enum AttackOutcome {
+    /// Nothing was there. With [`GameState::reveal_proximity`] on, `distance` is how many cells
+    /// away the nearest enemy ship still afloat is.
+    Miss { distance: Option<u32> },
+    /// The cell holds part of an enemy ship that is still afloat.
+    Hit { ship: GameObject },
+    /// The cell holds part of an enemy ship that is sunk, by this shot or a later one.
+    Sunk { ship: GameObject },
+}
+
+/// What [`GameState::apply`] did with an event, for the caller to animate or play a sound.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, Hash, Deserialize)]
+pub enum EventOutcome {
+    /// The event was applied, with nothing more to tell.
+    Applied,
+    /// What the attack did, right after it.
+    Attack(AttackOutcome),
+}
+
+/// The various reasons why a game could end
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, Hash, Deserialize)]
+pub enum EndGameReason {
+    PlayerLeft {
+        player_id: PlayerId,
+    },
+    PlayerWon {
+        winner: PlayerId,
+    },
+    /// Only the given team has ships afloat, in [`GameState::team_mode`].
+    TeamWon {
+        team: u8,
+    },
+    /// Every fleet was sunk by the same attack.
+    Draw,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cube(q: i32, r: i32) -> CubeCoords {
+        CubeCoords { q, r, s: -q - r }
+    }
+
+    fn apply(state: &mut GameState, event: GameEvent) {
+        if let Err(reason) = state.apply(event.clone()) {
+            panic!("{:?} rejected with {:?}", event, reason);
+        }
+    }
+
+    /// Two players in the lobby, with ids 1 and 2.
+    fn lobby() -> GameState {
+        let mut state = GameState::default();
+        for id in [1, 2] {
+            apply(
+                &mut state,
+                GameEvent::PlayerJoined {
+                    player_id: PlayerId(id),
+                    player_details: Player::new(format!("player {}", id)),
+                },
+            );
+        }
+        state
+    }
+
+    /// Both players ready in the lobby, about to set up the board.
+    fn ready_lobby() -> GameState {
+        let mut state = lobby();
+        for id in [1, 2] {
+            apply(
+                &mut state,
+                GameEvent::PlayerReady {
+                    player_id: PlayerId(id),
+                },
+            );
+        }
+        state
+    }
+
+    /// Both players ready, about to place their ships.
+    fn pregame() -> GameState {
+        let mut state = ready_lobby();
+        apply(&mut state, GameEvent::SetupBoard);
+        state
+    }
+
+    /// A ship placed at `row` of the column q = -3.
+    fn place(player_id: PlayerId, ship_type: GameObject, row: i32) -> GameEvent {
+        GameEvent::ShipPlaced {
+            player_id,
+            ship_type,
+            at: cube(-3, row),
+            rotation: 0,
+        }
+    }
+
+    /// Places the whole fleet of `player_id` in garage order along the column q = -3, from
+    /// `row` down, each ship starting on the row after the end of the one before.
+    fn place_fleet(state: &mut GameState, player_id: PlayerId, row: i32) {
+        let mut row = row;
+        for ship_type in state.garage_order() {
+            apply(state, place(player_id, ship_type, row));
+            row += ship_type.cells() as i32;
+        }
+    }
+
+    /// Both fleets placed at the same spots and the game begun.
+    fn in_game() -> GameState {
+        let mut state = pregame();
+        for id in [1, 2] {
+            place_fleet(&mut state, PlayerId(id), -4);
+        }
+        for id in [1, 2] {
+            apply(
+                &mut state,
+                GameEvent::PlacementCommitted {
+                    player_id: PlayerId(id),
+                },
+            );
+        }
+        apply(
+            &mut state,
+            GameEvent::BeginGame {
+                first_player: PlayerId(1),
+            },
+        );
+        state
+    }
+
+    /// The current player and the other one.
+    fn turn(state: &GameState) -> (PlayerId, PlayerId) {
+        let player = state.cur_player.unwrap();
+        let other = state.active_player_ids().find(|p| *p != player).unwrap();
+        (player, other)
+    }
+
+    #[test]
+    fn begin_game_waits_for_both_players_ready() {
+        let mut state = lobby();
+        let begin = GameEvent::BeginGame {
+            first_player: PlayerId(1),
+        };
+        assert_eq!(
+            state.validate_detailed(&begin),
+            Err(RejectReason::PlayersNotReady)
+        );
+        apply(
+            &mut state,
+            GameEvent::PlayerReady {
+                player_id: PlayerId(1),
+            },
+        );
+        assert_eq!(
+            state.validate_detailed(&begin),
+            Err(RejectReason::PlayersNotReady)
+        );
+        apply(
+            &mut state,
+            GameEvent::PlayerReady {
+                player_id: PlayerId(2),
+            },
+        );
+        assert_ne!(
+            state.validate_detailed(&begin),
+            Err(RejectReason::PlayersNotReady)
+        );
+    }
+
+    #[test]
+    fn repeated_attack_is_rejected() {
+        let mut state = in_game();
+        let (player, other) = turn(&state);
+        let at = cube(5, 0);
+        apply(
+            &mut state,
+            GameEvent::Attack {
+                player_id: player,
+                at,
+            },
+        );
+        apply(
+            &mut state,
+            GameEvent::Attack {
+                player_id: other,
+                at: cube(5, 1),
+            },
+        );
+        assert_eq!(
+            state.validate_detailed(&GameEvent::Attack {
+                player_id: player,
+                at
+            }),
+            Err(RejectReason::AlreadyAttacked)
+        );
+        // shots are tracked per player, so a cell the other player fired at is still open
+        assert!(state.validade(&GameEvent::Attack {
+            player_id: player,
+            at: cube(5, 1),
+        }));
+    }
+
+    #[test]
+    fn can_player_act_in_each_stage() {
+        let state = lobby();
+        assert!(!state.can_player_act(&PlayerId(1)));
+        assert!(!state.can_player_act(&PlayerId(2)));
+
+        let mut state = pregame();
+        assert!(state.can_player_act(&PlayerId(1)));
+        place_fleet(&mut state, PlayerId(1), -4);
+        // nothing left in the garage
+        assert!(!state.can_player_act(&PlayerId(1)));
+        assert!(state.can_player_act(&PlayerId(2)));
+
+        let mut state = in_game();
+        let (player, other) = turn(&state);
+        assert!(state.can_player_act(&player));
+        assert!(!state.can_player_act(&other));
+
+        apply(
+            &mut state,
+            GameEvent::EndGame {
+                reason: EndGameReason::PlayerWon { winner: player },
+            },
+        );
+        assert!(!state.can_player_act(&player));
+        assert!(!state.can_player_act(&other));
+    }
+
+    #[test]
+    fn attack_off_the_board_is_rejected() {
+        let state = in_game();
+        let (player, _) = turn(&state);
+        let radius = state.board_radius as i32;
+        for at in [
+            cube(radius + 1, 0),
+            // off the q + r + s = 0 plane
+            CubeCoords { q: 1, r: 1, s: 1 },
+            // would overflow when measured
+            CubeCoords {
+                q: i32::MIN,
+                r: i32::MAX,
+                s: 1,
+            },
+        ] {
+            assert_eq!(
+                state.validate_detailed(&GameEvent::Attack {
+                    player_id: player,
+                    at
+                }),
+                Err(RejectReason::OutOfBounds)
+            );
+        }
+        assert!(state.validade(&GameEvent::Attack {
+            player_id: player,
+            at: cube(radius, 0),
+        }));
+    }
+
+    /// Pins the wire format of every GameEvent, which clients outside of this crate read.
+    #[test]
+    fn game_event_json_shape() {
+        let player_id = PlayerId(3);
+        let at = cube(1, -2);
+        let cases = [
+            (GameEvent::SetupBoard, r#"{"type":"SetupBoard"}"#),
+            (
+                GameEvent::BeginGame {
+                    first_player: player_id,
+                },
+                r#"{"type":"BeginGame","first_player":3}"#,
+            ),
+            (
+                GameEvent::EndGame {
+                    reason: EndGameReason::PlayerWon { winner: player_id },
+                },
+                r#"{"type":"EndGame","reason":{"PlayerWon":{"winner":3}}}"#,
+            ),
+            (
+                GameEvent::PlayerJoined {
+                    player_id,
+                    player_details: Player::new("ada".to_string()),
+                },
+                concat!(
+                    r#"{"type":"PlayerJoined","player_id":3,"#,
+                    r#""player_details":{"name":"ada","color":[1.0,1.0,1.0,1.0],"team":0}}"#
+                ),
+            ),
+            (
+                GameEvent::PlayerDisconnected { player_id },
+                r#"{"type":"PlayerDisconnected","player_id":3}"#,
+            ),
+            (
+                GameEvent::PlayerReady { player_id },
+                r#"{"type":"PlayerReady","player_id":3}"#,
+            ),
+            (
+                GameEvent::ShipMove { player_id, at },
+                r#"{"type":"ShipMove","player_id":3,"at":{"q":1,"r":-2,"s":1}}"#,
+            ),
+            (
+                GameEvent::ShipPlaced {
+                    player_id,
+                    ship_type: GameObject::Cruizer,
+                    at,
+                    rotation: 2,
+                },
+                concat!(
+                    r#"{"type":"ShipPlaced","player_id":3,"ship_type":"Cruizer","#,
+                    r#""at":{"q":1,"r":-2,"s":1},"rotation":2}"#
+                ),
+            ),
+            (
+                GameEvent::PlaceMultiple {
+                    player_id,
+                    placements: vec![(at, 4)],
+                },
+                r#"{"type":"PlaceMultiple","player_id":3,"placements":[[{"q":1,"r":-2,"s":1},4]]}"#,
+            ),
+            (
+                GameEvent::Attack { player_id, at },
+                r#"{"type":"Attack","player_id":3,"at":{"q":1,"r":-2,"s":1}}"#,
+            ),
+            (
+                GameEvent::PlacementCommitted { player_id },
+                r#"{"type":"PlacementCommitted","player_id":3}"#,
+            ),
+            (
+                GameEvent::UndoPlacement { player_id },
+                r#"{"type":"UndoPlacement","player_id":3}"#,
+            ),
+            (
+                GameEvent::EndTurn { player_id },
+                r#"{"type":"EndTurn","player_id":3}"#,
+            ),
+            (
+                GameEvent::RequestRematch { player_id },
+                r#"{"type":"RequestRematch","player_id":3}"#,
+            ),
+        ];
+        for (event, json) in cases {
+            assert_eq!(serde_json::to_string(&event).unwrap(), json);
+            assert_eq!(serde_json::from_str::<GameEvent>(json).unwrap(), event);
+        }
+    }
+
+    #[test]
+    fn board_too_small_for_the_fleet_is_rejected() {
+        let mut state = ready_lobby();
+        state.board_radius = 1;
+        assert!(state.board_cells() < state.fleet.total_cells());
+        assert_eq!(
+            state.validate_detailed(&GameEvent::SetupBoard),
+            Err(RejectReason::BoardTooSmall)
+        );
+
+        // islands leave fewer cells to place ships on
+        let mut state = ready_lobby();
+        state.board_radius = 2;
+        assert!(state.validade(&GameEvent::SetupBoard));
+        state.islands = CubeCoords::ZERO.range(1).into_iter().collect();
+        assert_eq!(state.board_cells(), 12);
+        // one cell short of the 11 the fleet covers
+        state.islands.extend([cube(2, 0), cube(-2, 0)]);
+        assert_eq!(
+            state.validate_detailed(&GameEvent::SetupBoard),
+            Err(RejectReason::BoardTooSmall)
+        );
+    }
+
+    #[test]
+    fn overlapping_placements_are_rejected() {
+        let mut state = pregame();
+        let ships = state.garage_order().0;
+        apply(&mut state, place(PlayerId(1), ships[0], -4));
+        // the second ship would start on the last cell of the first
+        let stacked = place(PlayerId(1), ships[1], -1);
+        assert_eq!(
+            state.validate_detailed(&stacked),
+            Err(RejectReason::ShipsOverlap)
+        );
+        assert_eq!(
+            state.validate_detailed(&GameEvent::PlaceMultiple {
+                player_id: PlayerId(2),
+                placements: vec![(cube(-3, -4), 0), (cube(-3, -1), 0)],
+            }),
+            Err(RejectReason::ShipsOverlap)
+        );
+        // the other player's fleet takes no room
+        apply(&mut state, place(PlayerId(2), ships[0], -4));
+        apply(&mut state, place(PlayerId(1), ships[1], 0));
+    }
+
+    #[test]
+    fn begin_game_is_rejected_once_the_game_began() {
+        let mut state = in_game();
+        let (player, other) = turn(&state);
+        apply(
+            &mut state,
+            GameEvent::Attack {
+                player_id: player,
+                at: cube(1, 0),
+            },
+        );
+        let before = state.clone();
+        assert_eq!(
+            state.apply(GameEvent::BeginGame {
+                first_player: player
+            }),
+            Err(RejectReason::WrongStage)
+        );
+        assert_eq!(state, before);
+        assert_eq!(turn(&state), (other, player));
+    }
+
+    #[test]
+    fn attack_sinking_the_last_fleets_is_a_draw() {
+        let mut state = in_game();
+        let (player, other) = turn(&state);
+        // attacks never hit the attacker's own side, so their fleet goes down beforehand
+        for ship in state.player_ships.get_mut(&player).unwrap() {
+            for (_, armor) in ship.cells.iter_mut() {
+                *armor = 0;
+            }
+        }
+        // and the other fleet is left with a single cell afloat
+        let ships = state.player_ships.get_mut(&other).unwrap();
+        let last = ships[0].cells[0].0;
+        for ship in ships.iter_mut() {
+            for (cell, armor) in ship.cells.iter_mut() {
+                *armor = if *cell == last { 1 } else { 0 };
+            }
+        }
+
+        assert_eq!(
+            state.apply(GameEvent::Attack {
+                player_id: player,
+                at: last,
+            }),
+            Ok(EventOutcome::Attack(AttackOutcome::Sunk {
+                ship: GameObject::Cruizer
+            }))
+        );
+        assert_eq!(state.stage, GameStage::Ended);
+        assert_eq!(
+            state.last_event(),
+            Some(&GameEvent::EndGame {
+                reason: EndGameReason::Draw
+            })
+        );
+    }
+
+    #[test]
+    fn end_turn_is_rejected_off_turn() {
+        let mut state = in_game();
+        let (player, other) = turn(&state);
+        assert_eq!(
+            state.validate_detailed(&GameEvent::EndTurn { player_id: other }),
+            Err(RejectReason::NotYourTurn)
+        );
+        apply(&mut state, GameEvent::EndTurn { player_id: player });
+        assert_eq!(state.cur_player, Some(other));
+    }
+
+    #[test]
+    fn rejected_apply_leaves_the_state_unchanged() {
+        let mut state = in_game();
+        let (_, other) = turn(&state);
+        let before = state.clone();
+        assert_eq!(
+            state.apply(GameEvent::Attack {
+                player_id: other,
+                at: cube(0, 0),
+            }),
+            Err(RejectReason::NotYourTurn)
+        );
+        assert_eq!(state, before);
+    }
+
+    #[test]
+    fn failed_consume_leaves_the_state_unchanged() {
+        let mut state = pregame();
+        let player_id = PlayerId(1);
+        let valid = |i: i32| (cube(-3, -4 + 4 * i), 0);
+        let before = state.clone();
+
+        // the last ship of the batch doesn't fit in the garage
+        let too_many = GameEvent::PlaceMultiple {
+            player_id,
+            placements: (0..=state.fleet.len() as i32).map(valid).collect(),
+        };
+        assert_eq!(
+            state.apply(too_many.clone()),
+            Err(RejectReason::NoShipsLeft)
+        );
+        assert_eq!(
+            state.consume(&too_many),
+            Err(ConsumeError::NoShipsLeft(player_id))
+        );
+        assert_eq!(state, before);
+
+        // the last ship of the batch is rotated past a full turn
+        let mut placements: Vec<(CubeCoords, u32)> = (0..2).map(valid).collect();
+        placements[1].1 = u32::MAX;
+        let bad_rotation = GameEvent::PlaceMultiple {
+            player_id,
+            placements,
+        };
+        assert_eq!(
+            state.apply(bad_rotation.clone()),
+            Err(RejectReason::InvalidRotation)
+        );
+        assert_eq!(
+            state.consume(&bad_rotation),
+            Err(ConsumeError::InvalidRotation(player_id))
+        );
+        assert_eq!(state, before);
+        assert!(state.player_ships[&player_id].is_empty());
+
+        // the game doesn't begin, nor change stage, without an opponent
+        state
+            .consume(&GameEvent::PlayerDisconnected {
+                player_id: PlayerId(2),
+            })
+            .unwrap();
+        assert_eq!(
+            state.consume(&GameEvent::BeginGame {
+                first_player: player_id
+            }),
+            Err(ConsumeError::NoOpponent(player_id))
+        );
+        assert_eq!(state.stage, GameStage::PreGame);
+    }
+
+    #[test]
+    fn simultaneous_placement_takes_no_turns() {
+        let mut state = pregame();
+        let ships = state.garage_order().0;
+        apply(&mut state, place(PlayerId(2), ships[0], -4));
+        apply(&mut state, place(PlayerId(2), ships[1], 0));
+        apply(&mut state, place(PlayerId(1), ships[0], -4));
+    }
+
+    #[test]
+    fn alternating_placement_takes_turns() {
+        let mut state = ready_lobby();
+        state.placement_mode = PlacementMode::Alternating;
+        apply(&mut state, GameEvent::SetupBoard);
+        let ships = state.garage_order().0;
+
+        assert_eq!(
+            state.validate_detailed(&place(PlayerId(2), ships[0], -4)),
+            Err(RejectReason::NotYourTurn)
+        );
+        apply(&mut state, place(PlayerId(1), ships[0], -4));
+        assert_eq!(
+            state.validate_detailed(&place(PlayerId(1), ships[1], 0)),
+            Err(RejectReason::NotYourTurn)
+        );
+        apply(&mut state, place(PlayerId(2), ships[0], -4));
+        apply(&mut state, place(PlayerId(1), ships[1], 0));
+
+        // holding the placement turn doesn't allow moving ships before the game
+        assert_eq!(state.cur_player, Some(PlayerId(2)));
+        assert_eq!(
+            state.validate_detailed(&GameEvent::ShipMove {
+                player_id: PlayerId(2),
+                at: cube(0, 0),
+            }),
+            Err(RejectReason::WrongStage)
+        );
+    }
+
+    #[test]
+    fn removed_cells_are_off_the_board() {
+        let mut state = ready_lobby();
+        let hole = cube(-3, -2);
+        state.holes.insert(hole);
+        apply(&mut state, GameEvent::SetupBoard);
+        let ship_type = state.garage_order().0[0];
+
+        // on the hole itself, and with the hole under the rest of the ship
+        for row in [-2, -4] {
+            assert_eq!(
+                state.validate_detailed(&place(PlayerId(1), ship_type, row)),
+                Err(RejectReason::OutOfBounds)
+            );
+        }
+        assert!(state.validade(&place(PlayerId(1), ship_type, 0)));
+
+        let map = state.hex_map(1.0, 0.1).unwrap();
+        assert!(!map.contains(&hole));
+        assert_eq!(map.len() as u32, state.board_cells());
+    }
+
+    #[test]
+    fn ship_move_off_the_board_is_rejected() {
+        let mut state = in_game();
+        state.holes.insert(cube(4, 0));
+        let (player, _) = turn(&state);
+        let radius = state.board_radius as i32;
+        for at in [cube(4, 0), cube(radius + 1, 0)] {
+            assert_eq!(
+                state.validate_detailed(&GameEvent::ShipMove {
+                    player_id: player,
+                    at
+                }),
+                Err(RejectReason::OutOfBounds)
+            );
+        }
+        assert!(state.validade(&GameEvent::ShipMove {
+            player_id: player,
+            at: cube(3, 0),
+        }));
+    }
+
+    #[test]
+    fn turns_cycle_through_players_in_id_order() {
+        let mut state = lobby();
+        apply(
+            &mut state,
+            GameEvent::PlayerJoined {
+                player_id: PlayerId(3),
+                player_details: Player::new("player 3".to_string()),
+            },
+        );
+        // the rules only begin two player games, so the game is set going by hand
+        state.stage = GameStage::InGame;
+        state.cur_player = Some(PlayerId(1));
+
+        let mut order = Vec::new();
+        for _ in 0..4 {
+            order.push(state.cur_player.unwrap());
+            state.end_turn();
+        }
+        assert_eq!(order, [1, 2, 3, 1].map(PlayerId));
+
+        // players that left are skipped
+        state.players.remove(&PlayerId(2));
+        state.cur_player = Some(PlayerId(1));
+        let mut order = Vec::new();
+        for _ in 0..3 {
+            order.push(state.cur_player.unwrap());
+            state.end_turn();
+        }
+        assert_eq!(order, [1, 3, 1].map(PlayerId));
+    }
+
+    #[test]
+    fn stage_transitions() {
+        use GameStage::*;
+        for (from, to) in [
+            (Lobby, PreGame),
+            (PreGame, InGame),
+            (InGame, Paused),
+            (Paused, InGame),
+            (InGame, Ended),
+            (Lobby, Ended),
+            (Ended, Lobby),
+            (PreGame, PreGame),
+        ] {
+            assert!(
+                GameStage::can_transition(from, to),
+                "{:?} -> {:?}",
+                from,
+                to
+            );
+        }
+        for (from, to) in [
+            (Lobby, InGame),
+            (PreGame, Lobby),
+            (InGame, PreGame),
+            (Ended, InGame),
+            (Lobby, Paused),
+        ] {
+            assert!(
+                !GameStage::can_transition(from, to),
+                "{:?} -> {:?}",
+                from,
+                to
+            );
+        }
+
+        // consume refuses an illegal transition without changing the stage
+        let mut state = lobby();
+        assert_eq!(
+            state.consume(&GameEvent::BeginGame {
+                first_player: PlayerId(1)
+            }),
+            Err(ConsumeError::IllegalTransition {
+                from: Lobby,
+                to: InGame
+            })
+        );
+        assert_eq!(state.stage, Lobby);
+    }
+
+    /// Cells no ship covers, to attack without ending the game.
+    fn misses() -> impl Iterator<Item = CubeCoords> {
+        CubeCoords::ZERO.range(4).into_iter().filter(|at| at.q > 0)
+    }
+
+    #[test]
+    fn second_player_gets_an_extra_attack_on_their_first_turn() {
+        for balance in [false, true] {
+            let mut state = in_game();
+            state.balance_second_player = balance;
+            let (first, second) = turn(&state);
+            let mut misses = misses();
+
+            apply(
+                &mut state,
+                GameEvent::Attack {
+                    player_id: first,
+                    at: misses.next().unwrap(),
+                },
+            );
+            apply(
+                &mut state,
+                GameEvent::Attack {
+                    player_id: second,
+                    at: misses.next().unwrap(),
+                },
+            );
+            let extra = GameEvent::Attack {
+                player_id: second,
+                at: misses.next().unwrap(),
+            };
+            if balance {
+                apply(&mut state, extra);
+            } else {
+                assert_eq!(
+                    state.validate_detailed(&extra),
+                    Err(RejectReason::NotYourTurn)
+                );
+            }
+            assert_eq!(state.cur_player, Some(first));
+
+            // only on their first turn
+            apply(
+                &mut state,
+                GameEvent::Attack {
+                    player_id: first,
+                    at: misses.next().unwrap(),
+                },
+            );
+            apply(
+                &mut state,
+                GameEvent::Attack {
+                    player_id: second,
+                    at: misses.next().unwrap(),
+                },
+            );
+            assert_eq!(state.cur_player, Some(first));
+        }
+    }
+
+    #[test]
+    fn rematch_waits_for_both_players() {
+        let mut state = in_game();
+        let (winner, other) = turn(&state);
+        apply(
+            &mut state,
+            GameEvent::EndGame {
+                reason: EndGameReason::PlayerWon { winner },
+            },
+        );
+
+        apply(&mut state, GameEvent::RequestRematch { player_id: other });
+        assert_eq!(state.stage, GameStage::Ended);
+        assert_eq!(
+            state.validate_detailed(&GameEvent::RequestRematch { player_id: other }),
+            Err(RejectReason::RematchAlreadyRequested)
+        );
+
+        apply(&mut state, GameEvent::RequestRematch { player_id: winner });
+        assert_eq!(state.stage, GameStage::Lobby);
+        assert_eq!(state.players.len(), 2);
+        assert_eq!(state.series_score.get(&winner), Some(&1));
+        assert!(state.player_ships.is_empty());
+    }
+
+    #[test]
+    fn placement_onto_an_island_is_rejected() {
+        let mut state = pregame();
+        let ship_type = state.garage_order().0[0];
+        // the ship covers (-3, -4) to (-3, -1), the island sits under its tail
+        state.islands.insert(cube(-3, -1));
+        assert_eq!(
+            state.validate_detailed(&place(PlayerId(1), ship_type, -4)),
+            Err(RejectReason::BlockedTerrain)
+        );
+        assert_eq!(
+            state.validate_detailed(&GameEvent::PlaceMultiple {
+                player_id: PlayerId(1),
+                placements: vec![(cube(-3, -4), 0)],
+            }),
+            Err(RejectReason::BlockedTerrain)
+        );
+        // islands stay on the board for attacks
+        assert!(state.is_on_board(&cube(-3, -1)));
+        assert!(state.validade(&place(PlayerId(1), ship_type, 0)));
+    }
+
+    /// The current player sinks the first ship of the other one, who misses in between.
+    fn sink_first_ship(state: &mut GameState) {
+        let (player, other) = turn(state);
+        let ship = state.player_ships[&other][0].clone();
+        let mut misses = misses();
+        for (cell, armor) in ship.cells {
+            for _ in 0..armor {
+                apply(
+                    state,
+                    GameEvent::Attack {
+                        player_id: player,
+                        at: cell,
+                    },
+                );
+                if state.stage == GameStage::InGame {
+                    apply(
+                        state,
+                        GameEvent::Attack {
+                            player_id: other,
+                            at: misses.next().unwrap(),
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sinking_the_flagship_wins_under_sink_flagship() {
+        let mut state = in_game();
+        let (player, other) = turn(&state);
+        assert!(state.player_ships[&other][0].is_flagship);
+        assert!(!state.player_ships[&other][1].is_flagship);
+        sink_first_ship(&mut state);
+        assert!(state.is_flagship_sunk(&other));
+        // the rest of the fleet keeps the game going
+        assert_eq!(state.stage, GameStage::InGame);
+
+        let mut state = in_game();
+        state.win_condition = WinCondition::SinkFlagship;
+        sink_first_ship(&mut state);
+        assert_eq!(state.stage, GameStage::Ended);
+        assert_eq!(
+            state.last_event(),
+            Some(&GameEvent::EndGame {
+                reason: EndGameReason::PlayerWon { winner: player }
+            })
+        );
+    }
+
+    #[test]
+    fn out_of_turn_attack_forfeits_under_forfeit() {
+        for policy in [InvalidActionPolicy::Reject, InvalidActionPolicy::Forfeit] {
+            let mut state = in_game();
+            state.invalid_action_policy = policy;
+            let (player, other) = turn(&state);
+            let out_of_turn = GameEvent::Attack {
+                player_id: other,
+                at: cube(0, 0),
+            };
+            // apply can't tell who sent the event, so no one forfeits
+            assert_eq!(
+                state.apply(out_of_turn.clone()),
+                Err(RejectReason::NotYourTurn)
+            );
+            assert_eq!(state.stage, GameStage::InGame);
+            assert_eq!(
+                state.apply_from(out_of_turn, other),
+                Err(RejectReason::NotYourTurn)
+            );
+            if policy == InvalidActionPolicy::Reject {
+                assert_eq!(state.stage, GameStage::InGame);
+                continue;
+            }
+            assert_eq!(state.stage, GameStage::Ended);
+            assert_eq!(
+                state.last_event(),
+                Some(&GameEvent::EndGame {
+                    reason: EndGameReason::PlayerWon { winner: player }
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn sending_an_event_as_the_opponent_forfeits_the_sender() {
+        let mut state = in_game();
+        state.invalid_action_policy = InvalidActionPolicy::Forfeit;
+        let (player, other) = turn(&state);
+        // a valid attack, but on behalf of the player whose turn it is
+        let spoofed = GameEvent::Attack {
+            player_id: player,
+            at: cube(1, 0),
+        };
+        assert_eq!(
+            state.apply_from(spoofed, other),
+            Err(RejectReason::WrongSender)
+        );
+        assert_eq!(
+            state.last_event(),
+            Some(&GameEvent::EndGame {
+                reason: EndGameReason::PlayerWon { winner: player }
+            })
+        );
+    }
+
+    #[test]
+    fn undo_placement_gives_the_ship_back() {
+        let mut state = pregame();
+        let player_id = PlayerId(1);
+        let ships = state.garage_order().0;
+        let undo = GameEvent::UndoPlacement { player_id };
+        assert_eq!(
+            state.validate_detailed(&undo),
+            Err(RejectReason::NothingToUndo)
+        );
+
+        apply(&mut state, place(player_id, ships[0], -4));
+        apply(&mut state, place(player_id, ships[1], 0));
+        apply(&mut state, undo);
+        assert_eq!(state.player_ships[&player_id].len(), 1);
+        // the ship taken back is the next one to place again
+        assert_eq!(
+            state.validate_detailed(&place(player_id, ships[2], 2)),
+            Err(RejectReason::WrongShipType)
+        );
+        apply(&mut state, place(player_id, ships[1], 2));
+
+        let mut state = ready_lobby();
+        state.placement_mode = PlacementMode::Alternating;
+        apply(&mut state, GameEvent::SetupBoard);
+        apply(&mut state, place(player_id, ships[0], -4));
+        assert_eq!(
+            state.validate_detailed(&GameEvent::UndoPlacement { player_id }),
+            Err(RejectReason::UndoNotAllowed)
+        );
+    }
+
+    #[test]
+    fn ships_are_placed_in_garage_order() {
+        let mut state = ready_lobby();
+        state.shuffle_garage_seed = Some(7);
+        apply(&mut state, GameEvent::SetupBoard);
+        let order = state.garage_order();
+        assert_eq!(order, state.fleet.shuffled(7));
+
+        let wrong = order.0.iter().find(|&&ship| ship != order.0[0]).unwrap();
+        assert_eq!(
+            state.validate_detailed(&place(PlayerId(1), *wrong, -4)),
+            Err(RejectReason::WrongShipType)
+        );
+        // both players follow the same order
+        place_fleet(&mut state, PlayerId(1), -4);
+        place_fleet(&mut state, PlayerId(2), -4);
+    }
+
+    #[test]
+    fn reveal_proximity_tells_the_distance_of_a_miss() {
+        for (reveal_proximity, distance) in [(true, Some(2)), (false, None)] {
+            let mut state = in_game();
+            state.reveal_proximity = reveal_proximity;
+            let (player, _) = turn(&state);
+            // the nearest cell afloat is the flagship's at (-3, -3)
+            assert_eq!(
+                state.apply(GameEvent::Attack {
+                    player_id: player,
+                    at: cube(-1, -3),
+                }),
+                Ok(EventOutcome::Attack(AttackOutcome::Miss { distance }))
+            );
+        }
+    }
+
+    #[test]
+    fn apply_tells_what_an_attack_did() {
+        let mut state = pregame();
+        let ship_type = state.garage_order().0[0];
+        assert_eq!(
+            state.apply(place(PlayerId(1), ship_type, -4)),
+            Ok(EventOutcome::Applied)
+        );
+
+        let mut state = in_game();
+        let (player, other) = turn(&state);
+        let boat = state.player_ships[&other].last().unwrap().clone();
+        assert_eq!(boat.ship_type, GameObject::Boat);
+        let mut misses = misses();
+        let expected = [
+            (
+                misses.next().unwrap(),
+                AttackOutcome::Miss { distance: None },
+            ),
+            (
+                boat.cells[0].0,
+                AttackOutcome::Hit {
+                    ship: GameObject::Boat,
+                },
+            ),
+            (
+                boat.cells[1].0,
+                AttackOutcome::Sunk {
+                    ship: GameObject::Boat,
+                },
+            ),
+        ];
+        for (at, outcome) in expected {
+            assert_eq!(
+                state.apply(GameEvent::Attack {
+                    player_id: player,
+                    at,
+                }),
+                Ok(EventOutcome::Attack(outcome))
+            );
+            apply(
+                &mut state,
+                GameEvent::Attack {
+                    player_id: other,
+                    at: misses.next().unwrap(),
+                },
+            );
+        }
+    }
+
+    #[test]
+    fn placement_past_the_fleet_is_rejected() {
+        let mut state = pregame();
+        let player_id = PlayerId(1);
+        place_fleet(&mut state, player_id, -4);
+        let before = state.clone();
+
+        let extra = place(player_id, GameObject::Boat, 7);
+        assert_eq!(state.apply(extra.clone()), Err(RejectReason::NoShipsLeft));
+        assert_eq!(
+            state.consume(&extra),
+            Err(ConsumeError::NoShipsLeft(player_id))
+        );
+        assert_eq!(state, before);
+    }
 }
@@ -1,15 +1,31 @@
-use std::ops::{Add, Sub};
+use std::{
+    cmp::Ordering,
+    collections::VecDeque,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::{Add, Sub},
+    str::FromStr,
+};
+
+use super::{layout::HexLayout, CUBE_NEIGHBORS, HEX_CONFIG_MAX_RADIUS};
 
 use bevy::{
     prelude::*,
     render::{mesh::Indices, render_resource::PrimitiveTopology},
-    utils::HashMap,
+    utils::{HashMap, HashSet},
 };
 use serde::{Deserialize, Serialize};
 
 #[derive(Default, Resource)]
 pub struct MouseCubePos(pub CubeCoords);
 
+/// The hex under the mouse, kept apart from the GameState since hovering commits nothing.
+/// None while the mouse is off the board or over the padding between hexes.
+#[derive(Default, Resource)]
+pub struct HoverState {
+    pub coords: Option<CubeCoords>,
+}
+
 #[derive(Clone, Copy, Debug, Hash, Default, Serialize, Deserialize)]
 pub struct CubeCoords {
     pub q: i32,
@@ -20,6 +36,8 @@ pub struct CubeCoords {
 impl CubeCoords {
     pub const ZERO: Self = Self { q: 0, r: 0, s: 0 };
 
+    /// Hex distance: the number of steps to a neighbor it takes to go from one cell to the
+    /// other. This is the distance to use unless something can only move along diagonals.
     pub fn distance(&self, other: &CubeCoords) -> u32 {
         let dist = *other - *self;
         (dist.q.abs() + dist.r.abs() + dist.s.abs()) as u32 / 2
@@ -28,16 +46,73 @@ impl CubeCoords {
         self.distance(&CubeCoords::ZERO)
     }
 
+    /// Same as [`CubeCoords::distance`], named after the fact that `other` lies on the ring of
+    /// that radius around `self`, see [`CubeCoords::ring`].
+    pub fn ring_distance(&self, other: &CubeCoords) -> u32 {
+        self.distance(other)
+    }
+
+    /// Number of steps along [`CUBE_DIAGONALS`](super::CUBE_DIAGONALS) it takes to go from one
+    /// cell to the other, for pieces that only move diagonally. Diagonals reach a third of the
+    /// cells, None if `other` is not one of them.
+    pub fn diagonal_distance(&self, other: &CubeCoords) -> Option<u32> {
+        let dist = *other - *self;
+        // the diagonals form a hex grid of their own, whose neighbors are the diagonals
+        if (dist.q - dist.r) % 3 != 0 {
+            return None;
+        }
+        let q = (dist.q - dist.r) / 3;
+        let r = (dist.q + 2 * dist.r) / 3;
+        Some(CubeCoords { q, r, s: -q - r }.magnitude())
+    }
+
+    /// Rotates the coordinates a sixth of a turn counterclockwise around the origin.
     pub fn rotate_left(&mut self) {
-        self.q = -self.s;
-        self.r = -self.q;
-        self.s = -self.r;
+        let CubeCoords { q, r, s } = *self;
+        self.q = -s;
+        self.r = -q;
+        self.s = -r;
     }
 
+    /// Rotates the coordinates a sixth of a turn clockwise around the origin.
     pub fn rotate_right(&mut self) {
-        self.q = -self.r;
-        self.r = -self.s;
-        self.s = -self.q;
+        let CubeCoords { q, r, s } = *self;
+        self.q = -r;
+        self.r = -s;
+        self.s = -q;
+    }
+
+    /// The coordinates turned `steps` sixths of a turn clockwise around the origin, negative
+    /// steps turning them counterclockwise.
+    pub fn rotate_n(&self, steps: i32) -> CubeCoords {
+        let CubeCoords { q, r, s } = *self;
+        match steps.rem_euclid(6) {
+            0 => CubeCoords { q, r, s },
+            1 => CubeCoords {
+                q: -r,
+                r: -s,
+                s: -q,
+            },
+            2 => CubeCoords { q: s, r: q, s: r },
+            3 => CubeCoords {
+                q: -q,
+                r: -r,
+                s: -s,
+            },
+            4 => CubeCoords { q: r, r: s, s: q },
+            _ => CubeCoords {
+                q: -s,
+                r: -q,
+                s: -r,
+            },
+        }
+    }
+
+    /// Rotates the coordinates a sixth of a turn clockwise around `center`.
+    pub fn rotate_right_around(&mut self, center: &CubeCoords) {
+        let mut offset = *self - *center;
+        offset.rotate_right();
+        *self = *center + offset;
     }
 
     pub fn scalar_mul(&mut self, scalar: i32) {
@@ -45,6 +120,75 @@ impl CubeCoords {
         self.r *= scalar;
         self.s *= scalar;
     }
+
+    /// Point reflection through `center`, the same as a half turn around it.
+    pub fn reflect_through(&self, center: &CubeCoords) -> CubeCoords {
+        *center + *center - *self
+    }
+
+    /// Hexes exactly `radius` steps away, going around these coordinates. A radius of 0 is
+    /// just these coordinates.
+    pub fn ring(&self, radius: u32) -> Vec<CubeCoords> {
+        let mut ring = Vec::with_capacity(6 * radius as usize);
+        self.ring_into(radius, &mut ring);
+        ring
+    }
+
+    /// Same as [`CubeCoords::ring`], filling `out` instead so its allocation can be reused.
+    pub fn ring_into(&self, radius: u32, out: &mut Vec<CubeCoords>) {
+        out.clear();
+        if radius == 0 {
+            out.push(*self);
+            return;
+        }
+        let mut start = CUBE_NEIGHBORS[4];
+        start.scalar_mul(radius as i32);
+        let mut coords = *self + start;
+        for dir in CUBE_NEIGHBORS {
+            for _ in 0..radius {
+                out.push(coords);
+                coords = coords + dir;
+            }
+        }
+    }
+
+    /// Hexes at most `radius` steps away, these coordinates included.
+    pub fn range(&self, radius: u32) -> Vec<CubeCoords> {
+        let mut range = Vec::with_capacity((3 * radius * (radius + 1) + 1) as usize);
+        self.range_into(radius, &mut range);
+        range
+    }
+
+    /// Same as [`CubeCoords::range`], filling `out` instead so its allocation can be reused.
+    pub fn range_into(&self, radius: u32, out: &mut Vec<CubeCoords>) {
+        out.clear();
+        let n = radius as i32;
+        for q in -n..=n {
+            for r in (-n).max(-q - n)..=n.min(-q + n) {
+                out.push(*self + CubeCoords { q, r, s: -q - r });
+            }
+        }
+    }
+}
+
+impl fmt::Display for CubeCoords {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},{},{}", self.q, self.r, self.s)
+    }
+}
+
+/// Axial (q, r) coordinates, s is derived from them.
+impl From<(i32, i32)> for CubeCoords {
+    fn from((q, r): (i32, i32)) -> Self {
+        Self { q, r, s: -q - r }
+    }
+}
+
+impl From<(i32, i32, i32)> for CubeCoords {
+    fn from((q, r, s): (i32, i32, i32)) -> Self {
+        debug_assert_eq!(q + r + s, 0, "cube coordinates must sum to zero");
+        Self { q, r, s }
+    }
 }
 
 impl Eq for CubeCoords {}
@@ -53,6 +197,18 @@ impl PartialEq for CubeCoords {
         self.q == other.q && self.r == other.r && self.s == other.s
     }
 }
+/// Orders coordinates by q, then r, so sets of them iterate and serialize the same way on every
+/// run.
+impl Ord for CubeCoords {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.q, self.r, self.s).cmp(&(other.q, other.r, other.s))
+    }
+}
+impl PartialOrd for CubeCoords {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
 impl Add for CubeCoords {
     type Output = Self;
 
@@ -76,9 +232,77 @@ impl Sub for CubeCoords {
     }
 }
 
+/// Why a string could not be parsed into [`CubeCoords`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseCoordsError(pub String);
+
+impl fmt::Display for ParseCoordsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid coordinates: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseCoordsError {}
+
+impl FromStr for CubeCoords {
+    type Err = ParseCoordsError;
+
+    /// Parse either the "B7" letter-number offset notation, where the letter is the column
+    /// and the number the row counting from 1, or a "q,r,s" cube triplet.
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let err = || ParseCoordsError(input.to_string());
+        let input = input.trim();
+
+        if input.contains(',') {
+            let values = input
+                .split(',')
+                .map(|v| v.trim().parse::<i32>())
+                .collect::<Result<Vec<i32>, _>>()
+                .map_err(|_| err())?;
+            return match values[..] {
+                [q, r, s] if q + r + s == 0 => Ok(CubeCoords { q, r, s }),
+                _ => Err(err()),
+            };
+        }
+
+        let mut chars = input.chars();
+        let col = match chars.next() {
+            Some(letter) if letter.is_ascii_alphabetic() => {
+                letter.to_ascii_uppercase() as i32 - 'A' as i32
+            }
+            _ => return Err(err()),
+        };
+        let row = chars.as_str().parse::<i32>().map_err(|_| err())?;
+        if row < 1 {
+            return Err(err());
+        }
+        Ok(offset_to_axial_coords(col, row - 1))
+    }
+}
+
 #[derive(Component, Copy, Clone)]
 pub struct Hex(pub HexStatus);
 
+/// Why a Hexagon could not be created.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HexagonError {
+    InvalidSize(f32),
+    InvalidPadding(f32),
+}
+
+impl fmt::Display for HexagonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HexagonError::InvalidSize(size) => write!(f, "hexagon size {} is not positive", size),
+            HexagonError::InvalidPadding(padding) => {
+                write!(f, "hexagon padding {} is negative", padding)
+            }
+        }
+    }
+}
+
+impl std::error::Error for HexagonError {}
+
 #[derive(Clone, Debug)]
 pub struct Hexagon {
     pub size: f32,
@@ -87,11 +311,43 @@ pub struct Hexagon {
     pub width: f32,
     pub coords: Option<CubeCoords>,
     pub layer: f32,
+    /// Angle of corner 0 in degrees, counterclockwise from the x axis. Only multiples of 60
+    /// keep the flat topped shape the layout and [`Hexagon::contains_point`] expect.
+    pub start_angle: f32,
 }
 
 impl Hexagon {
-    /// Create a new Hexagon struct
+    /// Create a new Hexagon struct. A non-positive size or negative padding is clamped, with a
+    /// warning; use [`Hexagon::try_new`] to reject them instead.
     pub fn new(size: f32, padding: f32, coords: Option<CubeCoords>, layer: f32) -> Self {
+        match Self::try_new(size, padding, coords, layer) {
+            Ok(hex) => hex,
+            Err(err) => {
+                warn!("{}, clamping it", err);
+                let size = if size > 0.0 { size } else { f32::EPSILON };
+                let padding = if padding >= 0.0 { padding } else { 0.0 };
+                Self::build(size, padding, coords, layer)
+            }
+        }
+    }
+
+    /// Create a new Hexagon struct, refusing a non-positive size or negative padding.
+    pub fn try_new(
+        size: f32,
+        padding: f32,
+        coords: Option<CubeCoords>,
+        layer: f32,
+    ) -> Result<Self, HexagonError> {
+        if size.is_nan() || size <= 0.0 {
+            return Err(HexagonError::InvalidSize(size));
+        }
+        if padding.is_nan() || padding < 0.0 {
+            return Err(HexagonError::InvalidPadding(padding));
+        }
+        Ok(Self::build(size, padding, coords, layer))
+    }
+
+    fn build(size: f32, padding: f32, coords: Option<CubeCoords>, layer: f32) -> Self {
         Hexagon {
             size,
             padding,
@@ -99,12 +355,22 @@ impl Hexagon {
             width: 2.0 * (size + padding),
             coords,
             layer, // neighbors: None,
+            start_angle: 0.0,
         }
     }
 
-    /// Return the Vec2 coordinate of point i in a Hexagon
+    /// The same Hexagon with its corner 0 at `start_angle` degrees, see
+    /// [`Hexagon::start_angle`].
+    pub fn with_start_angle(mut self, start_angle: f32) -> Self {
+        self.start_angle = start_angle;
+        self
+    }
+
+    /// Return the Vec2 coordinate of point i in a Hexagon. Corners go counterclockwise, 60
+    /// degrees apart, from corner 0 at [`Hexagon::start_angle`], which is to the right of
+    /// the center by default.
     fn hex_corner_pos(&self, i: usize) -> Vec2 {
-        let angle = 60.0_f32.to_radians() * i as f32;
+        let angle = (self.start_angle + 60.0 * i as f32).to_radians();
         return Vec2 {
             x: self.size * angle.cos(),
             y: self.size * angle.sin(),
@@ -113,24 +379,96 @@ impl Hexagon {
 
     /// Generate a ['MaterialMeshBundle'] based on Hexagon coordinates and size.
     pub fn to_mesh(&self) -> Mesh {
-        let mut vectors = Vec::with_capacity(8);
+        self.to_mesh_subdivided(0)
+    }
+
+    /// Same as [`Hexagon::to_mesh`], with `subdivisions` more rings of six vertices between
+    /// the center and the corners, evenly spaced. The extra triangles let a radial gradient or
+    /// a displacement shade smoothly. 0 gives the plain mesh of [`Hexagon::to_mesh`].
+    pub fn to_mesh_subdivided(&self, subdivisions: u32) -> Mesh {
+        let rings = subdivisions + 1;
+        let mut vectors = Vec::with_capacity(1 + 6 * rings as usize);
         vectors.push([0.0, 0.0, 0.0]);
-        let mut indices = Vec::new();
+        for ring in 1..=rings {
+            let scale = ring as f32 / rings as f32;
+            for i in 0..6 {
+                let vec2d_pos = self.hex_corner_pos(i) * scale;
+                trace!("{:?}", vec2d_pos);
+                vectors.push([vec2d_pos.x, vec2d_pos.y, 0.0]);
+            }
+        }
+
+        // a fan around the center, then two triangles for each side of every band between rings
+        let vertex = |ring: u32, i: u32| 1 + (ring - 1) * 6 + i % 6;
+        let mut indices = Vec::with_capacity(18 * (2 * rings as usize - 1));
         for i in 0..6 {
-            let vec2d_pos = self.hex_corner_pos(i);
-            trace!("{:?}", vec2d_pos);
-            vectors.push([vec2d_pos.x, vec2d_pos.y, 0.0]);
-            indices.push(0);
-            indices.push(i as u32 + 1);
-            if i < 5 {
-                indices.push(i as u32 + 2);
-            } else {
-                indices.push(1);
+            indices.extend([0, vertex(1, i), vertex(1, i + 1)]);
+        }
+        for ring in 1..rings {
+            for i in 0..6 {
+                let (inner, inner_next) = (vertex(ring, i), vertex(ring, i + 1));
+                let (outer, outer_next) = (vertex(ring + 1, i), vertex(ring + 1, i + 1));
+                indices.extend([inner, outer, outer_next]);
+                indices.extend([inner, outer_next, inner_next]);
+            }
+        }
+
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 0.0, 1.0]; vectors.len()]);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, vectors);
+        mesh.set_indices(Some(Indices::U32(indices)));
+        mesh
+    }
+
+    /// Generate a ['Mesh'] of a hexagonal prism, with the top face at z = 0 and the bottom
+    /// face `thickness` below it. Every face has its own vertices so each side quad can carry
+    /// an outward-facing normal.
+    pub fn to_extruded_mesh(&self, thickness: f32) -> Mesh {
+        let mut vectors = Vec::with_capacity(38);
+        let mut normals = Vec::with_capacity(38);
+        let mut indices = Vec::new();
+
+        // top and bottom faces, as triangle fans around their centers
+        for (z, normal, is_top) in [
+            (0.0, [0.0, 0.0, 1.0], true),
+            (-thickness, [0.0, 0.0, -1.0], false),
+        ] {
+            let center = vectors.len() as u32;
+            vectors.push([0.0, 0.0, z]);
+            for i in 0..6 {
+                let vec2d_pos = self.hex_corner_pos(i);
+                vectors.push([vec2d_pos.x, vec2d_pos.y, z]);
+            }
+            normals.extend([normal; 7]);
+            for i in 0..6 {
+                let current = center + 1 + i;
+                let next = center + 1 + (i + 1) % 6;
+                // the bottom face is wound the other way round so it faces down
+                match is_top {
+                    true => indices.extend([center, current, next]),
+                    false => indices.extend([center, next, current]),
+                }
             }
         }
+
+        // side quads, facing away from the hexagon center
+        for i in 0..6 {
+            let start = self.hex_corner_pos(i);
+            let end = self.hex_corner_pos((i + 1) % 6);
+            // halfway between the corners, which follow the start angle
+            let angle = (self.start_angle + 60.0 * (i as f32 + 0.5)).to_radians();
+            let base = vectors.len() as u32;
+            vectors.push([start.x, start.y, 0.0]);
+            vectors.push([end.x, end.y, 0.0]);
+            vectors.push([start.x, start.y, -thickness]);
+            vectors.push([end.x, end.y, -thickness]);
+            normals.extend([[angle.cos(), angle.sin(), 0.0]; 4]);
+            indices.extend([base + 2, base + 3, base + 1, base + 2, base + 1, base]);
+        }
+
         let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
         mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, vectors);
-        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 0.0, 1.0]; 7]);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
         mesh.set_indices(Some(Indices::U32(indices)));
         mesh
     }
@@ -141,16 +479,52 @@ impl Hexagon {
             .as_ref()
             .expect("Cannot return Vec3 for a hex without a coordinate");
 
-        // this is for axial coordinates
-        let y_offset = self.height * (coords.s as f32 + 0.5 * coords.q as f32);
-        let x_offset = 0.75 * self.width * coords.q as f32;
+        let pos = HexLayout::new(self.size, self.padding, self.layer).world_pos(*coords);
+        trace!("x: {:?}, y: {:?}", pos.x, pos.y);
+        pos
+    }
+
+    /// World position of the six corners of the Hexagon, in the same order as the mesh.
+    pub fn corners_world(&self) -> [Vec3; 6] {
+        let center = self.world_pos();
+        [0, 1, 2, 3, 4, 5].map(|i| {
+            let corner = self.hex_corner_pos(i);
+            Vec3::new(center.x + corner.x, center.y + corner.y, center.z)
+        })
+    }
+
+    /// Whether a point of the world plane lies inside the drawn hexagon. Hexes are drawn with
+    /// their `size` but laid out with `size + padding`, so points in the gap between two hexes
+    /// are inside neither.
+    pub fn contains_point(&self, point: Vec2) -> bool {
+        let center = self.world_pos();
+        let x = (point.x - center.x).abs();
+        let y = (point.y - center.y).abs();
+        let half_height = 3.0_f32.sqrt() / 2.0 * self.size;
+        y <= half_height && 3.0_f32.sqrt() * x + y <= 2.0 * half_height
+    }
 
-        // this is for offset coordinates only
-        // let y_offset = (coordinates[0] % 2) as f32 * self.height * 0.5;
-        // let x_offset = 0.75 * self.width;
+    /// Hex distance between two hexagons, the same whichever one it is called on.
+    /// Returns None if either hexagon has no coordinate.
+    pub fn distance(&self, other: &Hexagon) -> Option<u32> {
+        match (self.coords, other.coords) {
+            (Some(coords), Some(other_coords)) => Some(coords.distance(&other_coords)),
+            _ => None,
+        }
+    }
+}
 
-        trace!("x: {:?}, y: {:?}", x_offset, y_offset);
-        Vec3::new(x_offset, y_offset, self.layer)
+/// Two hexagons are the same tile when their coordinates match. Size, padding and the other
+/// geometry fields are ignored.
+impl Eq for Hexagon {}
+impl PartialEq for Hexagon {
+    fn eq(&self, other: &Self) -> bool {
+        self.coords == other.coords
+    }
+}
+impl Hash for Hexagon {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.coords.hash(state);
     }
 }
 
@@ -163,59 +537,453 @@ pub struct HexMapObjects(pub HashMap<CubeCoords, Entity>);
 #[derive(Debug, Component)]
 pub struct HexHover;
 
+/// Toggles the coordinate grid overlay used while developing. F3 flips it at runtime.
+#[derive(Debug, Default, Resource)]
+pub struct DrawHexDebug(pub bool);
+
+#[derive(Debug, Component)]
+pub struct HexDebug;
+
+/// A debug label kept on screen over this world position, the center of its hex.
+#[derive(Debug, Component)]
+pub struct HexDebugLabel(pub Vec3);
+
 #[derive(Debug, Component)]
 pub struct SpawnHover;
 
+/// The shape of a generated HexMap, and the coordinates used to lay it out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CoordinateSystem {
+    /// A hexagon shaped board of the given radius around the origin.
+    Axial(i32),
+    /// A rectangular board laid out in offset columns and rows.
+    Offset(OffsetType),
+    /// A triangle shaped board with the given number of hexes per side.
+    Triangle(i32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OffsetType {
+    /// (width, height) board where odd columns are shoved half a hex down.
+    EvenQ(i32, i32),
+}
+
+/// The cells of a board. Only their coordinates are stored, the Hexagon of each cell is
+/// computed from the shared [`HexLayout`] when asked for.
 #[derive(Debug, Resource)]
 pub struct HexMap {
-    pub total_hex_size: f32,
-    pub hexes: Vec<Hexagon>,
+    pub layout: HexLayout,
+    cells: Vec<CubeCoords>,
+    system: CoordinateSystem,
+    /// Position in `cells` of each coordinate.
+    index: HashMap<CubeCoords, usize>,
+    /// Position in `cells` of the neighbors of each cell, in [`CUBE_NEIGHBORS`] order.
+    neighbors: Vec<[Option<usize>; 6]>,
+    /// Hexes ships can't be placed on, see [`HexMap::with_neutral_zone`].
+    neutral: HashSet<CubeCoords>,
 }
 
 impl HexMap {
-    pub fn new_from_axial(radius: i32, hex_size: f32, padding: f32) -> Self {
-        let mut hexes = Vec::new();
-        for q in -radius..=radius {
-            for r in -radius..=radius {
-                let s: i32 = -r - q;
-                if s.abs() > radius {
-                    continue;
+    /// Generate a HexMap with the shape described by the given coordinate system, refusing
+    /// boards larger than [`HEX_CONFIG_MAX_RADIUS`].
+    pub fn new(
+        system: CoordinateSystem,
+        hex_size: f32,
+        padding: f32,
+    ) -> Result<Self, BoardSizeError> {
+        Self::new_bounded(system, HEX_CONFIG_MAX_RADIUS, hex_size, padding)
+    }
+
+    /// Same as [`HexMap::new`], with a custom limit for the radius, side or width and height
+    /// of the board.
+    pub fn new_bounded(
+        system: CoordinateSystem,
+        max_radius: i32,
+        hex_size: f32,
+        padding: f32,
+    ) -> Result<Self, BoardSizeError> {
+        let dimensions = match system {
+            CoordinateSystem::Axial(radius) => vec![radius],
+            CoordinateSystem::Offset(OffsetType::EvenQ(width, height)) => vec![width, height],
+            CoordinateSystem::Triangle(side) => vec![side],
+        };
+        for size in dimensions {
+            if size < 1 {
+                return Err(BoardSizeError::TooSmall(size));
+            }
+            if size > max_radius {
+                return Err(BoardSizeError::TooLarge { size, max_radius });
+            }
+        }
+
+        // go through Hexagon::new once, so an invalid size or padding is clamped as for any hex
+        let hex = Hexagon::new(hex_size, padding, None, 0.0);
+        let layout = HexLayout::new(hex.size, hex.padding, hex.layer);
+        let cells = match system {
+            CoordinateSystem::Axial(radius) => cells_from_axial(radius),
+            CoordinateSystem::Offset(offset_type) => cells_from_offset(offset_type),
+            CoordinateSystem::Triangle(side) => cells_from_triangle(side),
+        };
+        let mut map = HexMap {
+            layout,
+            cells,
+            system,
+            index: HashMap::new(),
+            neighbors: Vec::new(),
+            neutral: HashSet::new(),
+        };
+        map.reindex();
+        Ok(map)
+    }
+
+    /// Marks the columns less than `width` columns away from the center column as a neutral
+    /// zone between the two sides of the board. Ships can't be placed there, but it can still
+    /// be attacked. A width of 0 leaves the whole board open.
+    pub fn with_neutral_zone(mut self, width: u32) -> Self {
+        let center = self.center();
+        self.neutral = self
+            .coords_iter()
+            .filter(|coords| in_neutral_zone(coords, &center, width))
+            .collect();
+        self
+    }
+
+    /// Removes the given cells from the map, leaving holes in the board. Neighbor queries and
+    /// everything built on them skip the removed cells.
+    pub fn without_cells(mut self, holes: impl IntoIterator<Item = CubeCoords>) -> Self {
+        let holes: HashSet<CubeCoords> = holes.into_iter().collect();
+        self.cells.retain(|coords| !holes.contains(coords));
+        self.neutral.retain(|coords| !holes.contains(coords));
+        self.reindex();
+        self
+    }
+
+    /// Whether `coords` is one of the cells of the map.
+    pub fn contains(&self, coords: &CubeCoords) -> bool {
+        self.index.contains_key(coords)
+    }
+
+    /// Whether `coords` lies in the neutral zone of the board.
+    pub fn is_neutral(&self, coords: &CubeCoords) -> bool {
+        self.neutral.contains(coords)
+    }
+
+    /// Rebuild the coordinate lookup and the neighbor adjacency list from `cells`.
+    fn reindex(&mut self) {
+        self.index = self
+            .cells
+            .iter()
+            .enumerate()
+            .map(|(i, coords)| (*coords, i))
+            .collect();
+        self.neighbors = self
+            .cells
+            .iter()
+            .map(|coords| CUBE_NEIGHBORS.map(|dir| self.index.get(&(*coords + dir)).copied()))
+            .collect();
+    }
+
+    /// The coordinate system the map was generated with.
+    pub fn system(&self) -> CoordinateSystem {
+        self.system
+    }
+
+    /// Battleship style "B7" label of a cell, column letter first and row number counting
+    /// from 1. Only maps generated with offset coordinates have labels, and only for the first
+    /// 26 columns. Returns None for coordinates off the board.
+    pub fn cell_label(&self, coords: &CubeCoords) -> Option<String> {
+        if !matches!(self.system, CoordinateSystem::Offset(_)) {
+            return None;
+        }
+        self.get(coords)?;
+        let [col, row] = axial_to_offset_coords(*coords);
+        if !(0..26).contains(&col) {
+            return None;
+        }
+        Some(format!("{}{}", (b'A' + col as u8) as char, row + 1))
+    }
+
+    /// Coordinates of the hex in the middle of the map. Axial maps are centered on the origin,
+    /// offset maps on the middle column and row. An empty map is centered on the origin.
+    pub fn center(&self) -> CubeCoords {
+        if self.cells.is_empty() {
+            return CubeCoords::ZERO;
+        }
+        let coords: Vec<CubeCoords> = self.coords_iter().collect();
+        match self.system {
+            CoordinateSystem::Offset(_) => {
+                let offsets: Vec<[i32; 2]> =
+                    coords.into_iter().map(axial_to_offset_coords).collect();
+                let cols = offsets.iter().map(|[col, _]| *col);
+                let rows = offsets.iter().map(|[_, row]| *row);
+                let col = (cols.clone().min().unwrap() + cols.max().unwrap()) / 2;
+                let row = (rows.clone().min().unwrap() + rows.max().unwrap()) / 2;
+                offset_to_axial_coords(col, row)
+            }
+            _ => {
+                let n = coords.len() as f32;
+                let sum = coords
+                    .into_iter()
+                    .fold(CubeCoords::ZERO, |sum, coords| sum + coords);
+                cube_round(sum.q as f32 / n, sum.r as f32 / n, sum.s as f32 / n)
+            }
+        }
+    }
+
+    /// World position of [`HexMap::center`].
+    pub fn center_world(&self) -> Vec3 {
+        self.world_pos_of(&self.center()).unwrap_or(Vec3::ZERO)
+    }
+
+    /// The hex facing `coords` across the middle of the map, or None if it isn't on the map.
+    /// Axial and triangle maps mirror coordinates through [`HexMap::center`], which makes
+    /// axial ones, centered on the origin, simply negate them. Offset maps mirror the column
+    /// and the row about the middle column and row instead.
+    pub fn symmetric_partner(&self, coords: CubeCoords) -> Option<CubeCoords> {
+        let partner = match self.system {
+            CoordinateSystem::Offset(OffsetType::EvenQ(width, height)) => {
+                let [col, row] = axial_to_offset_coords(coords);
+                offset_to_axial_coords(width - 1 - col, height - 1 - row)
+            }
+            _ => coords.reflect_through(&self.center()),
+        };
+        Some(partner).filter(|partner| self.contains(partner))
+    }
+
+    /// World position of the hex at `coords` after turning the board half a turn around
+    /// [`HexMap::center_world`], the way the player seated across the board sees it. Only the
+    /// drawing moves, the coordinates keep naming the same cell.
+    pub fn mirrored_world_pos(&self, coords: CubeCoords) -> Vec3 {
+        self.mirror_world(self.layout.world_pos(coords))
+    }
+
+    /// Turns a point of the world half a turn around [`HexMap::center_world`], keeping its
+    /// height. Mirroring twice gives back the original point.
+    pub fn mirror_world(&self, pos: Vec3) -> Vec3 {
+        let center = self.center_world();
+        Vec3::new(2.0 * center.x - pos.x, 2.0 * center.y - pos.y, pos.z)
+    }
+
+    /// World position of the hex at `coords`. Coordinates off the board are placed as if the
+    /// board went on. Returns None if `coords` isn't a valid cube coordinate.
+    pub fn world_pos_of(&self, coords: &CubeCoords) -> Option<Vec3> {
+        if coords.q + coords.r + coords.s != 0 {
+            return None;
+        }
+        Some(self.layout.world_pos(*coords))
+    }
+
+    /// Coordinates of the hex containing a point of the world plane, on the map or not, for
+    /// the size of this map's hexes. Maps of every [`CoordinateSystem`] keep their cells in
+    /// cube coordinates laid out by the same [`HexLayout`], offset maps included, so the same
+    /// inverse serves them all. Offset maps get their (column, row) from the result with
+    /// [`axial_to_offset_coords`].
+    pub fn world_pos_to_coordinates(&self, point: Vec2) -> CubeCoords {
+        let frac = self.layout.pixel_to_hex(point);
+        cube_round(frac.x, frac.y, frac.z)
+    }
+
+    /// Hexagon drawn under a point of the world plane, if any. Unlike
+    /// [`HexMap::world_pos_to_coordinates`], points in the padding between hexes pick nothing.
+    pub fn pick(&self, point: Vec2) -> Option<Hexagon> {
+        self.get(&self.world_pos_to_coordinates(point))
+            .filter(|hex| hex.contains_point(point))
+    }
+
+    /// Hexagon at the given coordinates, if it is part of the map.
+    pub fn get(&self, coords: &CubeCoords) -> Option<Hexagon> {
+        self.index
+            .get(coords)
+            .map(|i| self.layout.hexagon(self.cells[*i]))
+    }
+
+    /// Position in [`HexMap::coords_iter`] of the hex at the given coordinates.
+    pub fn index_of(&self, coords: &CubeCoords) -> Option<usize> {
+        self.index.get(coords).copied()
+    }
+
+    /// Adjacency list of the map: for the hex at each position of [`HexMap::coords_iter`], the
+    /// positions of its neighbors in [`CUBE_NEIGHBORS`] order, None where a neighbor is off
+    /// the map.
+    pub fn neighbors_index(&self) -> &[[Option<usize>; 6]] {
+        &self.neighbors
+    }
+
+    /// Hexes on the border of the map, those with a neighbor off the map. Hexes next to a hole
+    /// count as well.
+    pub fn edge_coords(&self) -> Vec<CubeCoords> {
+        self.cells
+            .iter()
+            .zip(&self.neighbors)
+            .filter(|(_, neighbors)| neighbors.iter().any(Option::is_none))
+            .map(|(coords, _)| *coords)
+            .collect()
+    }
+
+    /// Hexes connected to `start` through neighbors satisfying `pred`, `start` included whether
+    /// it satisfies `pred` or not. Empty if `start` is off the map.
+    pub fn flood_region(
+        &self,
+        start: CubeCoords,
+        pred: impl Fn(CubeCoords) -> bool,
+    ) -> HashSet<CubeCoords> {
+        let mut region = HashSet::new();
+        let start = match self.index_of(&start) {
+            Some(i) => i,
+            None => return region,
+        };
+        region.insert(self.cells[start]);
+        let mut stack = vec![start];
+        while let Some(i) = stack.pop() {
+            for neighbor in self.neighbors[i].iter().flatten() {
+                let coords = self.cells[*neighbor];
+                if !region.contains(&coords) && pred(coords) {
+                    region.insert(coords);
+                    stack.push(*neighbor);
                 }
-                hexes.push(Hexagon::new(
-                    hex_size,
-                    padding,
-                    Some(CubeCoords { q, r, s }),
-                    0.0,
-                ));
             }
         }
-        HexMap {
-            total_hex_size: hex_size + padding,
-            hexes,
+        region
+    }
+
+    /// Number of steps from `from` to every hex reachable from it, going around the holes of
+    /// the map and the hexes `blocked` is true for, such as islands. `from` is included whether
+    /// it is blocked or not. Hexes cut off from `from` are left out, and so is everything if
+    /// `from` is off the map.
+    pub fn distance_field(
+        &self,
+        from: CubeCoords,
+        blocked: impl Fn(CubeCoords) -> bool,
+    ) -> HashMap<CubeCoords, i32> {
+        let mut distances = HashMap::new();
+        let start = match self.index_of(&from) {
+            Some(i) => i,
+            None => return distances,
+        };
+        distances.insert(from, 0);
+        let mut queue = VecDeque::from([(start, 0)]);
+        while let Some((i, distance)) = queue.pop_front() {
+            for neighbor in self.neighbors[i].iter().flatten() {
+                let coords = self.cells[*neighbor];
+                if !distances.contains_key(&coords) && !blocked(coords) {
+                    distances.insert(coords, distance + 1);
+                    queue.push_back((*neighbor, distance + 1));
+                }
+            }
         }
+        distances
+    }
+
+    /// Generate a hexagon shaped HexMap of the given radius, centered at the origin. Every hex
+    /// of the map has its `coords` set.
+    pub fn new_from_axial(
+        radius: i32,
+        hex_size: f32,
+        padding: f32,
+    ) -> Result<Self, BoardSizeError> {
+        Self::new(CoordinateSystem::Axial(radius), hex_size, padding)
+    }
+
+    /// Generate a triangle shaped HexMap, with its corner hex at the origin.
+    pub fn new_triangle(side: i32, hex_size: f32, padding: f32) -> Result<Self, BoardSizeError> {
+        Self::new(CoordinateSystem::Triangle(side), hex_size, padding)
+    }
+
+    /// Rings of hexes around `center`, from the ring of radius 0 outwards, each clipped to the
+    /// map. Ends with the ring holding the hex farthest from `center`, so rings with no hex on
+    /// the map are only yielded before it, across gaps in the map.
+    pub fn rings_from(&self, center: CubeCoords) -> impl Iterator<Item = Vec<CubeCoords>> + '_ {
+        let last = self
+            .cells
+            .iter()
+            .map(|coords| coords.distance(&center))
+            .max();
+        let radii = match last {
+            Some(last) => 0..last + 1,
+            None => 0..0,
+        };
+        radii.map(move |radius| {
+            ring_coords(center, radius)
+                .into_iter()
+                .filter(|coords| self.contains(coords))
+                .collect()
+        })
+    }
+
+    /// Mesh of a single hex of the map, centered on the origin. Every hex shares the same
+    /// layout, so one mesh can be added to the assets and drawn at each hex's world position.
+    pub fn shared_mesh(&self) -> Mesh {
+        Hexagon::new(
+            self.layout.size,
+            self.layout.padding,
+            None,
+            self.layout.layer,
+        )
+        .to_mesh()
+    }
+
+    /// A single mesh holding every hex of the map at its world position, so the whole board is
+    /// drawn at once. Each hex is colored by `color_fn` through the vertex colors, which a
+    /// [`StandardMaterial`] multiplies with its base color.
+    pub fn to_combined_mesh(&self, color_fn: impl Fn(CubeCoords) -> [f32; 4]) -> Mesh {
+        let mut vectors = Vec::with_capacity(7 * self.len());
+        let mut colors = Vec::with_capacity(7 * self.len());
+        let mut indices = Vec::with_capacity(18 * self.len());
+        for hex in self.iter() {
+            let first = vectors.len() as u32;
+            let color = color_fn(hex.coords.unwrap());
+            vectors.push(hex.world_pos().to_array());
+            for corner in hex.corners_world() {
+                vectors.push(corner.to_array());
+            }
+            colors.extend([color; 7]);
+            for i in 0..6 {
+                indices.push(first);
+                indices.push(first + i + 1);
+                indices.push(first + (i + 1) % 6 + 1);
+            }
+        }
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 0.0, 1.0]; vectors.len()]);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, vectors);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+        mesh.set_indices(Some(Indices::U32(indices)));
+        mesh
+    }
+
+    /// Iterate over the coordinates of every hex in the map.
+    pub fn coords_iter(&self) -> impl Iterator<Item = CubeCoords> + '_ {
+        self.cells.iter().copied()
+    }
+
+    /// Iterate over every hex in the map, in the same order as [`HexMap::coords_iter`].
+    pub fn iter(&self) -> impl Iterator<Item = Hexagon> + '_ {
+        self.cells.iter().map(|coords| self.layout.hexagon(*coords))
+    }
+
+    /// Number of hexes in the map.
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
     }
 
     // pub fn get_hex_from_pos(pos: Vec3) -> &Hexagon {}
     // pub fn coordinate_from_pos(pos: Vec2) -> [u32; 3] {}
 }
 
+/// Coordinates of the hex containing the world position `pos`, for hexes of
+/// `total_hex_size`, padding included. This is the inverse of [`Hexagon::world_pos`], see
+/// [`HexLayout::pixel_to_hex`].
 pub fn world_pos_to_coordinates(total_hex_size: f32, pos: Vec2) -> CubeCoords {
-    let basis_vec = Mat2::from_cols(
-        Vec2 {
-            x: 2.0 / 3.0,
-            y: -1.0 / 3.0,
-        },
-        Vec2 {
-            x: 0.,
-            y: 3_f32.sqrt() / 3.0,
-        },
-    );
-
-    let q_r = basis_vec * pos / total_hex_size;
-    cube_round(q_r.x, -q_r.x - q_r.y, q_r.y)
-}
-
-fn cube_round(q: f32, r: f32, s: f32) -> CubeCoords {
+    let frac = HexLayout::new(total_hex_size, 0.0, 0.0).pixel_to_hex(pos);
+    cube_round(frac.x, frac.y, frac.z)
+}
+
+/// Rounds fractional cube coordinates to the hex containing them.
+pub fn cube_round(q: f32, r: f32, s: f32) -> CubeCoords {
     let mut qr = q.round();
     let mut rr = r.round();
     let mut sr = s.round();
@@ -235,25 +1003,115 @@ fn cube_round(q: f32, r: f32, s: f32) -> CubeCoords {
         s: sr as i32,
     }
 }
-// fn hexes_from_offset(offset_type: OffsetType, size: f32) -> Vec<Hexagon> {
-//     let mut hex = Hexagon::new(size);
-//     let mut hexes = Vec::new();
-//     match offset_type {
-//         OffsetType::EvenQ(width, height) => {
-//             for i in 0..height {
-//                 for j in 0..width {
-//                     // TODO: convert Offset to Axial Coords
-//                     hex.coordinates = Some(offset_to_axial_coords(i, j));
-//                     hexes.push(hex.clone());
-//                 }
-//             }
-//         }
-//     };
-//     hexes
-// }
-// fn offset_to_axial_coords(x: i32, y: i32) -> CubeCoords {}
-
-// fn axial_to_offset_coords(x: u32, y: u32) -> [u32; 2] {}
+/// Why a HexMap could not be generated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoardSizeError {
+    TooSmall(i32),
+    TooLarge { size: i32, max_radius: i32 },
+}
+
+impl fmt::Display for BoardSizeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BoardSizeError::TooSmall(size) => write!(f, "board size {} is below 1", size),
+            BoardSizeError::TooLarge { size, max_radius } => {
+                write!(
+                    f,
+                    "board size {} is above the limit of {}",
+                    size, max_radius
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for BoardSizeError {}
+
+fn cells_from_axial(radius: i32) -> Vec<CubeCoords> {
+    let mut cells = Vec::new();
+    for q in -radius..=radius {
+        for r in -radius..=radius {
+            let s: i32 = -r - q;
+            if s.abs() > radius {
+                continue;
+            }
+            cells.push(CubeCoords { q, r, s });
+        }
+    }
+    cells
+}
+
+fn cells_from_triangle(side: i32) -> Vec<CubeCoords> {
+    let mut cells = Vec::new();
+    for q in 0..side {
+        for s in 0..side - q {
+            cells.push(CubeCoords { q, r: -q - s, s });
+        }
+    }
+    cells
+}
+
+fn cells_from_offset(offset_type: OffsetType) -> Vec<CubeCoords> {
+    let mut cells = Vec::new();
+    match offset_type {
+        OffsetType::EvenQ(width, height) => {
+            for row in 0..height {
+                for col in 0..width {
+                    cells.push(offset_to_axial_coords(col, row));
+                }
+            }
+        }
+    };
+    cells
+}
+
+/// Convert an even-q (column, row) offset pair to cube coordinates. Rows run along `s`, which
+/// is the vertical axis of [`Hexagon::world_pos`].
+pub fn offset_to_axial_coords(col: i32, row: i32) -> CubeCoords {
+    let q = col;
+    let s = row - (col + (col & 1)) / 2;
+    CubeCoords { q, r: -q - s, s }
+}
+
+/// Whether the cells form a single group of neighboring hexes. No cells at all count as
+/// connected.
+pub fn is_contiguous(cells: &[CubeCoords]) -> bool {
+    let cells: HashSet<CubeCoords> = cells.iter().copied().collect();
+    let start = match cells.iter().next() {
+        Some(start) => *start,
+        None => return true,
+    };
+    let mut region = HashSet::new();
+    region.insert(start);
+    let mut stack = vec![start];
+    while let Some(coords) = stack.pop() {
+        for dir in CUBE_NEIGHBORS {
+            let neighbor = coords + dir;
+            if cells.contains(&neighbor) && region.insert(neighbor) {
+                stack.push(neighbor);
+            }
+        }
+    }
+    region.len() == cells.len()
+}
+
+/// Hexes exactly `radius` steps away from `center`, going around it. A radius of 0 is just
+/// `center`.
+pub fn ring_coords(center: CubeCoords, radius: u32) -> Vec<CubeCoords> {
+    center.ring(radius)
+}
+
+/// Whether `coords` is less than `width` columns away from the column of `center`.
+pub fn in_neutral_zone(coords: &CubeCoords, center: &CubeCoords, width: u32) -> bool {
+    coords.q.abs_diff(center.q) < width
+}
+
+/// Convert cube coordinates back to the even-q (column, row) offset pair.
+pub fn axial_to_offset_coords(coords: CubeCoords) -> [i32; 2] {
+    let col = coords.q;
+    let row = coords.s + (coords.q + (coords.q & 1)) / 2;
+    [col, row]
+}
 
 #[derive(PartialEq, Copy, Clone)]
 pub enum HexStatus {
@@ -261,3 +1119,323 @@ pub enum HexStatus {
     Selected,
     Damage,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cube(q: i32, r: i32) -> CubeCoords {
+        CubeCoords { q, r, s: -q - r }
+    }
+
+    #[test]
+    fn distance_field_stops_at_an_island_splitting_the_board() {
+        let map = HexMap::new_from_axial(2, 1.0, 0.0).unwrap();
+        let island: HashSet<CubeCoords> = (-2..=2).map(|r| cube(0, r)).collect();
+        let field = map.distance_field(cube(-1, 0), |coords| island.contains(&coords));
+        assert!(field.keys().all(|coords| coords.q < 0));
+        assert_eq!(
+            field.len(),
+            map.coords_iter().filter(|coords| coords.q < 0).count()
+        );
+        assert_eq!(field[&cube(-1, 0)], 0);
+        assert_eq!(field[&cube(-2, 2)], 2);
+    }
+
+    #[test]
+    fn distance_field_goes_around_an_island() {
+        let map = HexMap::new_from_axial(2, 1.0, 0.0).unwrap();
+        let island: HashSet<CubeCoords> = (-2..=1).map(|r| cube(0, r)).collect();
+        let field = map.distance_field(cube(-1, 0), |coords| island.contains(&coords));
+        assert!(island.iter().all(|coords| !field.contains_key(coords)));
+        // through the gap at (0, 2) instead of the two steps straight across
+        assert_eq!(field[&cube(1, 0)], 5);
+        assert_eq!(field.len(), map.len() - island.len());
+    }
+
+    #[test]
+    fn oversized_board_is_rejected() {
+        assert_eq!(
+            HexMap::new_from_axial(100_000, 1.0, 0.0).err(),
+            Some(BoardSizeError::TooLarge {
+                size: 100_000,
+                max_radius: HEX_CONFIG_MAX_RADIUS,
+            })
+        );
+        assert_eq!(
+            HexMap::new_from_axial(0, 1.0, 0.0).err(),
+            Some(BoardSizeError::TooSmall(0))
+        );
+        assert!(HexMap::new_from_axial(HEX_CONFIG_MAX_RADIUS, 1.0, 0.0).is_ok());
+    }
+
+    /// Flood fills a radius 10 map both by hashing the coordinates of each neighbor and through
+    /// the precomputed [`HexMap::neighbors_index`]. Run it with
+    /// `cargo test --release -p store -- --ignored --nocapture` to see the timings.
+    #[test]
+    #[ignore]
+    fn bench_neighbors_index() {
+        use std::time::Instant;
+
+        let map = HexMap::new_from_axial(10, 1.0, 0.0).unwrap();
+        let runs = 200;
+
+        let hashed = Instant::now();
+        for _ in 0..runs {
+            let mut seen = HashSet::from([CubeCoords::ZERO]);
+            let mut stack = vec![CubeCoords::ZERO];
+            while let Some(coords) = stack.pop() {
+                for dir in CUBE_NEIGHBORS {
+                    let neighbor = coords + dir;
+                    if map.contains(&neighbor) && seen.insert(neighbor) {
+                        stack.push(neighbor);
+                    }
+                }
+            }
+            assert_eq!(seen.len(), map.len());
+        }
+        let hashed = hashed.elapsed();
+
+        let indexed = Instant::now();
+        for _ in 0..runs {
+            let mut seen = vec![false; map.len()];
+            let start = map.index_of(&CubeCoords::ZERO).unwrap();
+            seen[start] = true;
+            let mut stack = vec![start];
+            while let Some(i) = stack.pop() {
+                for neighbor in map.neighbors_index()[i].iter().flatten() {
+                    if !seen[*neighbor] {
+                        seen[*neighbor] = true;
+                        stack.push(*neighbor);
+                    }
+                }
+            }
+            assert!(seen.iter().all(|seen| *seen));
+        }
+        let indexed = indexed.elapsed();
+
+        println!(
+            "{} flood fills of {} hexes: {:?} hashed, {:?} indexed ({:.1}x)",
+            runs,
+            map.len(),
+            hashed,
+            indexed,
+            hashed.as_secs_f64() / indexed.as_secs_f64()
+        );
+    }
+
+    #[test]
+    fn points_in_the_padding_are_in_no_hex() {
+        let map = HexMap::new_from_axial(2, 1.0, 0.5).unwrap();
+        let center = map.get(&CubeCoords::ZERO).unwrap();
+        let neighbor = map.get(&cube(1, 0)).unwrap();
+        let gap = ((center.world_pos() + neighbor.world_pos()) / 2.0).truncate();
+
+        assert!(map.iter().all(|hex| !hex.contains_point(gap)));
+        assert!(map.pick(gap).is_none());
+        // the centers themselves are picked
+        assert_eq!(
+            map.pick(center.world_pos().truncate())
+                .and_then(|hex| hex.coords),
+            Some(CubeCoords::ZERO)
+        );
+    }
+
+    /// Bytes a map holds on the heap, from the capacity of its cells, neighbor table and index,
+    /// with cells of `cell_size` bytes. The index takes an entry and a control byte per bucket,
+    /// the way hashbrown lays it out.
+    fn heap_bytes(map: &HexMap, cell_size: usize) -> usize {
+        use std::mem::size_of;
+
+        let buckets = (map.index.capacity() * 8 / 7).next_power_of_two();
+        map.cells.capacity() * cell_size
+            + map.neighbors.capacity() * size_of::<[Option<usize>; 6]>()
+            + buckets * (size_of::<(CubeCoords, usize)>() + 1)
+    }
+
+    /// Backs the memory figures of keeping only coordinates per cell, measured on the whole
+    /// map of a radius 50 board against the same map holding a Hexagon per cell.
+    #[test]
+    fn map_cells_store_only_coordinates() {
+        use std::mem::size_of;
+
+        let map = HexMap::new_from_axial(50, 1.0, 0.1).unwrap();
+        assert_eq!(map.len(), 7651);
+        let coords = heap_bytes(&map, size_of::<CubeCoords>());
+        let hexagons = heap_bytes(&map, size_of::<Hexagon>());
+        // the neighbor table takes 96 bytes a cell either way, the cells 12 instead of 40
+        assert_eq!(coords / map.len(), 162);
+        assert_eq!(hexagons / map.len(), 192);
+    }
+
+    #[test]
+    fn disconnected_footprint_is_not_contiguous() {
+        assert!(!is_contiguous(&[cube(0, 0), cube(2, 0)]));
+        assert!(!is_contiguous(&[cube(0, 0), cube(1, 0), cube(3, -1)]));
+        assert!(is_contiguous(&[cube(0, 0), cube(1, 0), cube(1, 1)]));
+        // a single cell is trivially connected
+        assert!(is_contiguous(&[cube(4, -2)]));
+        assert!(is_contiguous(&[]));
+    }
+
+    #[test]
+    fn rotate_n_wraps_around_six_steps() {
+        let coords = cube(2, -1);
+        assert_eq!(coords.rotate_n(0), coords);
+        assert_eq!(coords.rotate_n(6), coords);
+        assert_eq!(coords.rotate_n(-1), coords.rotate_n(5));
+        assert_eq!(coords.rotate_n(13), coords.rotate_n(1));
+
+        let (mut right, mut left) = (coords, coords);
+        right.rotate_right();
+        left.rotate_left();
+        assert_eq!(coords.rotate_n(1), right);
+        assert_eq!(coords.rotate_n(-1), left);
+    }
+
+    #[test]
+    fn hex_centers_map_back_to_their_coordinates() {
+        let map = HexMap::new_from_axial(4, 1.0, 0.1).unwrap();
+        for hex in map.iter() {
+            let center = hex.world_pos().truncate();
+            assert_eq!(
+                world_pos_to_coordinates(hex.size + hex.padding, center),
+                hex.coords.unwrap()
+            );
+            assert_eq!(map.world_pos_to_coordinates(center), hex.coords.unwrap());
+        }
+    }
+
+    #[test]
+    fn neighbors_of_the_origin_are_one_spacing_away() {
+        let (size, padding) = (1.0, 0.1);
+        let origin = Hexagon::new(size, padding, Some(CubeCoords::ZERO), 0.0).world_pos();
+        let spacing = 3.0_f32.sqrt() * (size + padding);
+        for dir in CUBE_NEIGHBORS {
+            let neighbor = Hexagon::new(size, padding, Some(dir), 0.0).world_pos();
+            assert!((neighbor.distance(origin) - spacing).abs() < 1e-5);
+            // y points up while r grows downwards
+            assert!((neighbor.x - 1.5 * (size + padding) * dir.q as f32).abs() < 1e-5);
+            let y = -spacing * (dir.r as f32 + dir.q as f32 / 2.0);
+            assert!((neighbor.y - y).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn range_into_reuses_its_buffer() {
+        let mut buffer = Vec::new();
+        CubeCoords::ZERO.range_into(6, &mut buffer);
+        let (ptr, capacity) = (buffer.as_ptr(), buffer.capacity());
+        for center in CubeCoords::ZERO.range(3) {
+            center.range_into(6, &mut buffer);
+            assert_eq!(buffer, center.range(6));
+            center.ring_into(6, &mut buffer);
+            assert_eq!(buffer, center.ring(6));
+            // no reallocation once the buffer is large enough
+            assert_eq!((buffer.as_ptr(), buffer.capacity()), (ptr, capacity));
+        }
+    }
+
+    /// Fills the range of radius 6 around every hex of a radius 10 board, allocating a new Vec
+    /// each time or reusing one. Run it with
+    /// `cargo test --release -p store -- --ignored --nocapture` to see the timings.
+    #[test]
+    #[ignore]
+    fn bench_range_into() {
+        use std::time::Instant;
+
+        let centers = CubeCoords::ZERO.range(10);
+        let runs = 50;
+
+        let allocating = Instant::now();
+        let mut total = 0;
+        for _ in 0..runs {
+            for center in &centers {
+                total += center.range(6).len();
+            }
+        }
+        let allocating = allocating.elapsed();
+
+        let reusing = Instant::now();
+        let mut buffer = Vec::new();
+        for _ in 0..runs {
+            for center in &centers {
+                center.range_into(6, &mut buffer);
+                total -= buffer.len();
+            }
+        }
+        let reusing = reusing.elapsed();
+        assert_eq!(total, 0);
+
+        println!(
+            "{} ranges: {:?} allocating {} times, {:?} allocating once ({:.1}x)",
+            runs * centers.len(),
+            allocating,
+            runs * centers.len(),
+            reusing,
+            allocating.as_secs_f64() / reusing.as_secs_f64()
+        );
+    }
+
+    #[test]
+    fn edge_coords_of_a_radius_2_board() {
+        let map = HexMap::new_from_axial(2, 1.0, 0.0).unwrap();
+        let mut edge = map.edge_coords();
+        let mut ring = CubeCoords::ZERO.ring(2);
+        edge.sort();
+        ring.sort();
+        assert_eq!(edge, ring);
+
+        // the cells around a hole are on the edge as well
+        let map = HexMap::new_from_axial(2, 1.0, 0.0)
+            .unwrap()
+            .without_cells([CubeCoords::ZERO]);
+        let edge = map.edge_coords();
+        assert_eq!(edge.len(), 18);
+        assert!(CubeCoords::ZERO.ring(1).iter().all(|c| edge.contains(c)));
+    }
+
+    #[test]
+    fn mirroring_twice_gives_back_the_position() {
+        let map = HexMap::new_triangle(4, 1.0, 0.1).unwrap();
+        for coords in map.coords_iter() {
+            let pos = map.layout.world_pos(coords);
+            let mirrored = map.mirrored_world_pos(coords);
+            assert!(map.mirror_world(mirrored).distance(pos) < 1e-4);
+            assert_eq!(mirrored.z, pos.z);
+        }
+        // the center stays in place
+        let center = map.center_world();
+        assert!(map.mirror_world(center).distance(center) < 1e-4);
+    }
+
+    #[test]
+    fn hexagons_are_equal_by_coordinates() {
+        let small = Hexagon::new(1.0, 0.0, Some(cube(1, -1)), 0.0);
+        let large = Hexagon::new(3.0, 0.5, Some(cube(1, -1)), 2.0);
+        assert_eq!(small, large);
+        assert_ne!(small, Hexagon::new(1.0, 0.0, Some(cube(1, 0)), 0.0));
+
+        let set: HashSet<Hexagon> = [small, large].into_iter().collect();
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn world_positions_round_trip_in_every_coordinate_system() {
+        let systems = [
+            CoordinateSystem::Axial(4),
+            CoordinateSystem::Offset(OffsetType::EvenQ(6, 5)),
+            CoordinateSystem::Triangle(5),
+        ];
+        for system in systems {
+            let map = HexMap::new(system, 1.0, 0.1).unwrap();
+            for coords in map.coords_iter() {
+                let center = map.world_pos_of(&coords).unwrap().truncate();
+                assert_eq!(map.world_pos_to_coordinates(center), coords, "{system:?}");
+                // anywhere well inside the hex still picks it
+                let nudged = center + Vec2::new(0.3, -0.3);
+                assert_eq!(map.world_pos_to_coordinates(nudged), coords, "{system:?}");
+            }
+        }
+    }
+}
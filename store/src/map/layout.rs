@@ -0,0 +1,95 @@
+use bevy::prelude::{Vec2, Vec3};
+
+use super::components::{CubeCoords, Hexagon};
+
+/// The matrices turning axial coordinates into positions in hex sizes and back, see
+/// https://www.redblobgames.com/grids/hexagons/#hex-to-pixel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Orientation {
+    /// Forward matrix, x = f0 * q + f1 * r and y = f2 * q + f3 * r.
+    pub f0: f32,
+    pub f1: f32,
+    pub f2: f32,
+    pub f3: f32,
+    /// Inverse of the forward matrix, q = b0 * x + b1 * y and r = b2 * x + b3 * y.
+    pub b0: f32,
+    pub b1: f32,
+    pub b2: f32,
+    pub b3: f32,
+}
+
+impl Orientation {
+    /// Flat topped hexes, the only orientation [`Hexagon`] is drawn with.
+    pub const FLAT: Self = Self {
+        f0: 3.0 / 2.0,
+        f1: 0.0,
+        f2: 0.866_025_4,
+        f3: 1.732_050_8,
+        b0: 2.0 / 3.0,
+        b1: 0.0,
+        b2: -1.0 / 3.0,
+        b3: 0.577_350_26,
+    };
+}
+
+/// Size and padding shared by every hex of a HexMap, from which their geometry is computed.
+/// The world y axis points up while r grows downwards on screen, so y is flipped on the way
+/// in and out of the orientation matrices.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HexLayout {
+    pub orientation: Orientation,
+    pub size: f32,
+    pub padding: f32,
+    pub layer: f32,
+    /// World position of the hex at [`CubeCoords::ZERO`]. A Hexagon only knows its own
+    /// geometry and is drawn around the world origin, which is why a HexMap keeps it at zero.
+    pub origin: Vec2,
+}
+
+impl HexLayout {
+    /// A flat topped layout centered on the world origin.
+    pub fn new(size: f32, padding: f32, layer: f32) -> Self {
+        Self {
+            orientation: Orientation::FLAT,
+            size,
+            padding,
+            layer,
+            origin: Vec2::ZERO,
+        }
+    }
+
+    /// Size of a hex including its padding, which sets the spacing of the layout.
+    pub fn total_size(&self) -> f32 {
+        self.size + self.padding
+    }
+
+    /// The Hexagon laid out at `coords`.
+    pub fn hexagon(&self, coords: CubeCoords) -> Hexagon {
+        Hexagon::new(self.size, self.padding, Some(coords), self.layer)
+    }
+
+    /// World position of the hex at `coords`.
+    pub fn world_pos(&self, coords: CubeCoords) -> Vec3 {
+        self.hex_to_pixel(coords).extend(self.layer)
+    }
+
+    /// Position of the center of the hex at `coords` on the world plane.
+    pub fn hex_to_pixel(&self, coords: CubeCoords) -> Vec2 {
+        let o = &self.orientation;
+        let (q, r) = (coords.q as f32, coords.r as f32);
+        let x = (o.f0 * q + o.f1 * r) * self.total_size();
+        let y = (o.f2 * q + o.f3 * r) * self.total_size();
+        self.origin + Vec2::new(x, -y)
+    }
+
+    /// Fractional cube coordinates (q, r, s) of a point on the world plane, to be rounded to
+    /// the hex containing it with [`super::components::cube_round`].
+    pub fn pixel_to_hex(&self, pos: Vec2) -> Vec3 {
+        let o = &self.orientation;
+        let pt = (pos - self.origin) / self.total_size();
+        let (x, y) = (pt.x, -pt.y);
+        let q = o.b0 * x + o.b1 * y;
+        let r = o.b2 * x + o.b3 * y;
+        Vec3::new(q, r, -q - r)
+    }
+}
@@ -1,15 +1,20 @@
 pub mod components;
+pub mod layout;
 mod systems;
 
 use bevy::prelude::*;
 use components::*;
 use systems::*;
 
+use crate::GameState;
+
 pub const HEX_CONFIG_SIZE: f32 = 1.0;
 pub const HEX_CONFIG_PADDING: f32 = 0.1;
 pub const HEX_TOT_SIZE: f32 = HEX_CONFIG_SIZE + HEX_CONFIG_PADDING;
+pub const HEX_CONFIG_MAX_RADIUS: i32 = 64;
+pub const HEX_CONFIG_BOARD_RADIUS: i32 = 8;
 
-const CUBE_NEIGHBORS: [CubeCoords; 6] = [
+pub const CUBE_NEIGHBORS: [CubeCoords; 6] = [
     CubeCoords { q: 1, r: 0, s: -1 },
     CubeCoords { q: 1, r: -1, s: 0 },
     CubeCoords { q: 0, r: -1, s: 1 },
@@ -18,7 +23,7 @@ const CUBE_NEIGHBORS: [CubeCoords; 6] = [
     CubeCoords { q: 0, r: 1, s: -1 },
 ];
 
-const CUBE_DIAGONALS: [CubeCoords; 6] = [
+pub const CUBE_DIAGONALS: [CubeCoords; 6] = [
     CubeCoords { q: 2, r: -1, s: -1 },
     CubeCoords { q: 1, r: -2, s: 1 },
     CubeCoords { q: -1, r: -1, s: 2 },
@@ -27,19 +32,44 @@ const CUBE_DIAGONALS: [CubeCoords; 6] = [
     CubeCoords { q: 1, r: 1, s: -2 },
 ];
 
+/// The hex types and helpers most users of the map need, `use store::map::prelude::*;`.
+pub mod prelude {
+    pub use super::components::{
+        axial_to_offset_coords, cube_round, in_neutral_zone, offset_to_axial_coords, ring_coords,
+        world_pos_to_coordinates, BoardSizeError, CoordinateSystem, CubeCoords, HexMap, Hexagon,
+        HexagonError, OffsetType, ParseCoordsError,
+    };
+    pub use super::layout::{HexLayout, Orientation};
+    pub use super::{
+        CUBE_DIAGONALS, CUBE_NEIGHBORS, HEX_CONFIG_BOARD_RADIUS, HEX_CONFIG_MAX_RADIUS,
+        HEX_CONFIG_PADDING, HEX_CONFIG_SIZE, HEX_TOT_SIZE,
+    };
+}
+
 pub struct HexPlugin;
 impl Plugin for HexPlugin {
     fn build(&self, app: &mut App) {
+        // the board is drawn from the GameState, so both agree on which cells exist
+        let hex_map = match app.world.get_resource::<GameState>() {
+            Some(game_state) => game_state.hex_map(HEX_CONFIG_SIZE, HEX_CONFIG_PADDING),
+            None => GameState::default().hex_map(HEX_CONFIG_SIZE, HEX_CONFIG_PADDING),
+        };
         app.insert_resource(HexMapTiles::default())
             .insert_resource(HexMapObjects::default())
             // TODO: CHECK IF HEXMAP RESOURCE IS ACTUALLY NECESSARY.
-            .insert_resource(HexMap::new_from_axial(8, 1.0, 0.1))
+            .insert_resource(hex_map.unwrap())
             .insert_resource(MouseCubePos::default())
+            .insert_resource(HoverState::default())
+            .insert_resource(DrawHexDebug::default())
             // TODO: MOUSE CUBE POS NEED TO BE UPDATED FIRST
             .add_system(world_pos_to_cube_coords)
             .add_system(update_hover_hex)
             .add_system(hex_activate)
             .add_system(hex_draw_line)
+            .add_system(hex_debug_toggle)
+            .add_system(hex_debug_draw)
+            .add_system(hex_debug_label)
+            .add_system(hex_debug_label_follow)
             .add_startup_system(setup);
     }
 
@@ -63,12 +93,13 @@ fn setup(
 
     // Spawn pregame ui
 
-    // Spawn hexmap
-    for hex in &board_config.hexes {
+    // Spawn hexmap, every hex drawn with the same mesh
+    let mesh = meshes.add(board_config.shared_mesh());
+    for hex in board_config.iter() {
         let hex_pos = hex.world_pos();
         let entity = commands
             .spawn(MaterialMeshBundle {
-                mesh: meshes.add(hex.to_mesh()),
+                mesh: mesh.clone(),
                 material: materials.add(StandardMaterial {
                     base_color: Color::rgb(0.67, 0.67, 0.67),
                     unlit: true,
@@ -1,31 +1,38 @@
 use super::{
-    components::{world_pos_to_coordinates, CubeCoords},
-    Hex, HexHover, HexMapTiles, HexStatus, Hexagon, MouseCubePos, HEX_CONFIG_PADDING,
-    HEX_CONFIG_SIZE, HEX_TOT_SIZE,
+    components::CubeCoords, DrawHexDebug, Hex, HexDebug, HexDebugLabel, HexHover, HexMap,
+    HexMapTiles, HexStatus, Hexagon, HoverState, MouseCubePos, HEX_CONFIG_PADDING, HEX_CONFIG_SIZE,
 };
-use crate::{camera::MouseWorldPos, game_objects};
-use bevy::prelude::*;
+use crate::{
+    camera::{MainCamera, MouseWorldPos},
+    game_objects,
+};
+use bevy::{prelude::*, render::render_resource::PrimitiveTopology};
 
 pub fn world_pos_to_cube_coords(
     ms_pos: Res<MouseWorldPos>,
+    hex_map: Res<HexMap>,
     mut ms_coord_pos: ResMut<MouseCubePos>,
+    mut hover: ResMut<HoverState>,
 ) {
-    ms_coord_pos.0 = world_pos_to_coordinates(HEX_TOT_SIZE, ms_pos.0);
+    ms_coord_pos.0 = hex_map.world_pos_to_coordinates(ms_pos.0);
+    hover.coords = hex_map.pick(ms_pos.0).and_then(|hex| hex.coords);
 }
 
 pub fn update_hover_hex(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    ms_pos: Res<MouseWorldPos>,
     ms_coord: Res<MouseCubePos>,
     hex_board: Res<HexMapTiles>,
     mut query: Query<(&mut Transform, &mut Visibility), With<HexHover>>,
 ) {
+    let hex = Hexagon::new(HEX_CONFIG_SIZE, HEX_CONFIG_PADDING, Some(ms_coord.0), 1.0);
+    // the hover is hidden over the padding between hexes
     let is_entity = match hex_board.0.get(&ms_coord.0) {
-        Some(_) => true,
+        Some(_) => hex.contains_point(ms_pos.0),
         None => false,
     };
-    let hex = Hexagon::new(HEX_CONFIG_SIZE, HEX_CONFIG_PADDING, Some(ms_coord.0), 1.0);
     let hex_pos = hex.world_pos();
 
     match query.get_single_mut() {
@@ -157,3 +164,112 @@ fn hex_to_color(
 fn lerp(start: Vec3, end: Vec3, distance: f32) -> Vec3 {
     start + (end - start) * distance
 }
+
+pub fn hex_debug_toggle(kb_input: Res<Input<KeyCode>>, mut draw_debug: ResMut<DrawHexDebug>) {
+    if kb_input.just_pressed(KeyCode::F3) {
+        draw_debug.0 = !draw_debug.0;
+    }
+}
+
+/// Draws the outline of every hex on the board while [`DrawHexDebug`] is on.
+pub fn hex_debug_draw(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    draw_debug: Res<DrawHexDebug>,
+    board_config: Res<HexMap>,
+    query: Query<Entity, With<HexDebug>>,
+) {
+    if !draw_debug.is_changed() {
+        return;
+    }
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+    if !draw_debug.0 {
+        return;
+    }
+
+    // a single line list holding the six edges of each hex
+    let mut vectors = Vec::new();
+    for hex in board_config.iter() {
+        let corners = hex.corners_world();
+        for i in 0..6 {
+            vectors.push(corners[i].to_array());
+            vectors.push(corners[(i + 1) % 6].to_array());
+        }
+    }
+    let mut mesh = Mesh::new(PrimitiveTopology::LineList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 0.0, 1.0]; vectors.len()]);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, vectors);
+    commands
+        .spawn(MaterialMeshBundle {
+            mesh: meshes.add(mesh),
+            material: materials.add(StandardMaterial {
+                base_color: Color::YELLOW,
+                unlit: true,
+                ..default()
+            }),
+            transform: Transform::from_xyz(0.0, 0.0, 0.5),
+            ..default()
+        })
+        .insert(HexDebug);
+}
+
+/// Spawns the label of every hex as text over its center while [`DrawHexDebug`] is on. The
+/// labels are [`HexDebug`] entities too, so [`hex_debug_draw`] despawns them with the outlines.
+pub fn hex_debug_label(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    draw_debug: Res<DrawHexDebug>,
+    board_config: Res<HexMap>,
+) {
+    if !draw_debug.is_changed() || !draw_debug.0 {
+        return;
+    }
+    let style = TextStyle {
+        font: asset_server.load("Inconsolata.ttf"),
+        font_size: 14.0,
+        color: Color::YELLOW,
+    };
+    for hex in board_config.iter() {
+        let coords = hex.coords.unwrap();
+        let label = board_config
+            .cell_label(&coords)
+            .unwrap_or_else(|| coords.to_string());
+        commands
+            .spawn(
+                TextBundle::from_section(label, style.clone()).with_style(Style {
+                    position_type: PositionType::Absolute,
+                    ..default()
+                }),
+            )
+            .insert(HexDebugLabel(hex.world_pos()))
+            .insert(HexDebug);
+    }
+}
+
+/// Keeps the hex debug labels over their hexes as the camera moves, hiding those it can't see.
+pub fn hex_debug_label_follow(
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut labels: Query<(&HexDebugLabel, &mut Style, &mut Visibility)>,
+) {
+    let (camera, camera_transform) = match camera_query.get_single() {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+    for (label, mut style, mut visibility) in &mut labels {
+        match camera.world_to_viewport(camera_transform, label.0) {
+            // viewport positions start from the bottom left of the window
+            Some(pos) => {
+                style.position = UiRect {
+                    left: Val::Px(pos.x),
+                    bottom: Val::Px(pos.y),
+                    ..default()
+                };
+                visibility.is_visible = true;
+            }
+            None => visibility.is_visible = false,
+        }
+    }
+}